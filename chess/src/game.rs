@@ -1,6 +1,10 @@
-use shakmaty::{Chess, Move, Outcome, Position};
+use fastrand::Rng;
+use shakmaty::{
+    zobrist::{Zobrist64, ZobristHash},
+    Chess, EnPassantMode, Move, Outcome, Position, Role,
+};
 
-use crate::state::State;
+use mcts::State;
 
 #[derive(Default, Clone)]
 pub struct Game {
@@ -21,12 +25,39 @@ impl Game {
     }
 }
 
+pub fn role_value(role: Role) -> f32 {
+    match role {
+        Role::Pawn => 1.,
+        Role::Knight | Role::Bishop => 3.,
+        Role::Rook => 5.,
+        Role::Queen => 9.,
+        Role::King => 0.,
+    }
+}
+
+/// Material balance squashed into `[0, 1]`, from the perspective of the
+/// player who just moved, for [`ImplicitMinimax`](mcts::minimax::ImplicitMinimax)
+pub fn material_eval(game: &Game) -> f32 {
+    let board = game.pos.board();
+    let player = game.pos.turn().other();
+
+    let balance = board
+        .iter()
+        .map(|(_, piece)| match piece.color == player {
+            true => role_value(piece.role),
+            false => -role_value(piece.role),
+        })
+        .sum::<f32>();
+
+    1. / (1. + (-balance / 3.).exp())
+}
+
 impl State for Game {
     type Action = Move;
 
-    fn possible_actions(&self) -> Vec<Self::Action> {
+    fn possible_actions(&self, rng: &mut Rng) -> Vec<Self::Action> {
         let mut actions = self.pos.legal_moves().to_vec();
-        fastrand::shuffle(&mut actions);
+        rng.shuffle(&mut actions);
 
         actions
     }
@@ -48,13 +79,8 @@ impl State for Game {
     fn reward(&self, perspective: &Self) -> f32 {
         match self.pos.outcome() {
             Some(Outcome::Draw) => 0.5,
-            Some(Outcome::Decisive { winner }) => {
-                if winner == perspective.pos.turn().other() {
-                    1.
-                } else {
-                    0.
-                }
-            }
+            Some(Outcome::Decisive { winner }) if winner == perspective.pos.turn().other() => 1.,
+            Some(Outcome::Decisive { .. }) => 0.,
             // treat too long playouts as a loss
             _ => 0.,
         }
@@ -63,4 +89,23 @@ impl State for Game {
     fn is_terminal(&mut self, depth: usize) -> bool {
         self.cached_is_terminal.unwrap() || depth >= 30
     }
+
+    /// Captures and promotions first, read from the move alone so it stays cheap
+    fn action_prior(&self, action: &Self::Action) -> f32 {
+        let mut prior = 1.;
+
+        if let Some(captured) = action.capture() {
+            prior += role_value(captured);
+        }
+        if let Some(promotion) = action.promotion() {
+            prior += role_value(promotion);
+        }
+
+        prior
+    }
+
+    fn hash_key(&self) -> Option<u64> {
+        let Zobrist64(hash) = self.pos.zobrist_hash(EnPassantMode::Legal);
+        Some(hash)
+    }
 }
@@ -1,18 +1,24 @@
-use game::Game;
-use mcts::Mcts;
+use std::time::Duration;
+
+use game::{material_eval, Game};
+use mcts::{
+    hybrid::HybridMinimax,
+    minimax::ImplicitMinimax,
+    node::Proof,
+    policy,
+    progressive::{ProgressiveBias, ProgressiveWidening},
+    rollout, Mcts, SearchLimits,
+};
 use shakmaty::{fen::Fen, CastlingMode, Chess};
 
 pub mod game;
-pub mod mcts;
-pub mod node;
-pub mod state;
-pub mod tree;
 
 fn main() {
     let mut args = std::env::args();
     args.next();
 
-    let search_time = args.next().unwrap().parse::<u128>().unwrap();
+    let search_time = args.next().unwrap().parse::<u64>().unwrap();
+    let limits = SearchLimits::time(Duration::from_secs(search_time));
     let fen = args.next().unwrap();
     let pos: Chess = fen
         .parse::<Fen>()
@@ -21,7 +27,58 @@ fn main() {
         .unwrap();
     let game = Game::new(pos);
 
-    let mut searcher: Mcts<Game> = Mcts::default();
-    let m = searcher.search(search_time * 1000, game);
+    let policy_name = args.next().unwrap_or("ucb1".to_owned());
+    let c = args.next().map_or(1., |c| c.parse::<f32>().unwrap());
+    let threads = args.next().map_or(1, |n| n.parse::<usize>().unwrap());
+    // "tree" shares a single tree between all threads, "root" searches one tree per thread
+    let parallelism = args.next().unwrap_or("tree".to_owned());
+    let seed = args.next().map(|seed| seed.parse::<u64>().unwrap());
+    // weight of the material evaluation backed up by implicit minimax, 0 disables it
+    let minimax_weight = args.next().map_or(0., |w| w.parse::<f32>().unwrap());
+    // shallow minimax for tactics, e.g. "mr2" in rollouts, "ms3" at selection or "mb2" at expansion
+    let hybrid = args.next().map(|hybrid| {
+        let depth = hybrid[2..].parse::<usize>().unwrap();
+        match &hybrid[..2] {
+            "mr" => HybridMinimax::rollouts(depth),
+            "ms" => HybridMinimax::selection(depth, 10),
+            "mb" => HybridMinimax::expansion(depth),
+            _ => panic!("unknown hybrid minimax {hybrid}"),
+        }
+    });
+    // how simulations pick their moves: "uniform", "heuristic", "mast", "nst" or "lgr"
+    let rollout_policy = args.next().map(|name| {
+        rollout::from_name(&name).unwrap_or_else(|| panic!("unknown rollout policy {name}"))
+    });
+    let policy = policy::from_name(&policy_name, c).expect("unknown selection policy");
+
+    let mut searcher: Mcts<Game> = Mcts::with_policy(policy);
+    searcher.set_transpositions(true);
+    searcher.set_progressive_widening(Some(ProgressiveWidening::default()));
+    searcher.set_progressive_bias(Some(ProgressiveBias::default()));
+    searcher.set_report_interval(Some(Duration::from_secs(1)));
+    if let Some(seed) = seed {
+        searcher.set_seed(seed);
+    }
+    if minimax_weight > 0. {
+        searcher.set_implicit_minimax(Some(ImplicitMinimax::new(minimax_weight, material_eval)));
+    }
+    if let Some(hybrid) = hybrid {
+        searcher.set_hybrid_minimax(hybrid);
+    }
+    if let Some(rollout_policy) = rollout_policy {
+        searcher.set_rollout_policy(rollout_policy);
+    }
+    let m = match parallelism.as_str() {
+        _ if threads == 1 => searcher.search(&limits, game),
+        "tree" => searcher.search_parallel(&limits, game, threads),
+        "root" => searcher.search_root_parallel(&limits, game, threads),
+        _ => panic!("unknown parallelism {parallelism}"),
+    };
     println!("{m:?}");
+
+    match searcher.proven_result() {
+        Some(Proof::Win(plies)) => println!("mate in {}", plies.div_ceil(2)),
+        Some(Proof::Loss(plies)) => println!("mated in {}", plies / 2),
+        _ => {}
+    }
 }
@@ -1,4 +1,6 @@
 use crate::player::Player;
+use fastrand::Rng;
+use mcts::{final_move::FinalMoveSelection, rave::RaveSchedule, Mcts, State};
 use std::fmt::Display;
 
 const EMPTY_CELL: char = '.';
@@ -11,7 +13,6 @@ pub enum GameState {
 
 #[derive(Clone, Copy)]
 pub struct Game {
-    players: [Player; 2],
     turn: usize,
     // TODO: switch to bitboards
     grid: [[char; 7]; 6],
@@ -19,9 +20,8 @@ pub struct Game {
 }
 
 impl Game {
-    pub fn new(players: [Player; 2]) -> Self {
+    pub fn new() -> Self {
         Game {
-            players,
             turn: 0,
             grid: [[EMPTY_CELL; 7]; 6],
             last_move: 255,
@@ -32,15 +32,27 @@ impl Game {
         (self.grid.len(), self.grid[0].len())
     }
 
-    pub fn main_loop(&mut self, log: bool) {
+    pub fn main_loop(&mut self, players: &[Player; 2], log: bool) {
         if log {
             println!("{self}");
         }
 
-        while !self.is_terminal() {
-            let col = self.players[self.turn].get_move(self);
+        let mut searchers: [Mcts<Game>; 2] = Default::default();
+        for searcher in searchers.iter_mut() {
+            searcher.set_transpositions(true);
+            searcher.set_rave(Some(RaveSchedule::default()));
+            searcher
+                .set_final_move_selection(FinalMoveSelection::MaxRobustChild { extension: 0.5 });
+        }
+
+        while !Game::is_terminal(self) {
+            let col = players[self.turn].get_move(self, &mut searchers[self.turn]);
             self.do_move(col);
 
+            for searcher in searchers.iter_mut() {
+                searcher.advance_root(&[col]);
+            }
+
             if log {
                 println!("{self}");
             }
@@ -133,6 +145,75 @@ impl Game {
     }
 }
 
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
+    }
+}
+
+impl State for Game {
+    type Action = usize;
+
+    fn possible_actions(&self, rng: &mut Rng) -> Vec<Self::Action> {
+        let (_, ncols) = self.shape();
+        let mut actions = (0..ncols)
+            .filter(|&col| !self.is_filled_col(col))
+            .collect::<Vec<_>>();
+        rng.shuffle(&mut actions);
+
+        actions
+    }
+
+    fn apply_action(&self, action: Self::Action) -> Self {
+        let mut game = *self;
+        game.do_move(action);
+
+        game
+    }
+
+    fn last_action(&self) -> Option<Self::Action> {
+        (self.last_move != 255).then_some(self.last_move)
+    }
+
+    fn reward(&self, perspective: &Self) -> f32 {
+        match self.get_state() {
+            GameState::Win(winner) if winner == 1 - perspective.turn() => 1.,
+            GameState::Win(_) => 0.,
+            _ => 0.5,
+        }
+    }
+
+    fn is_terminal(&mut self, _depth: usize) -> bool {
+        !matches!(self.get_state(), GameState::Playing)
+    }
+
+    /// Centre columns first
+    fn action_prior(&self, action: &Self::Action) -> f32 {
+        let (_, ncols) = self.shape();
+        (ncols / 2 + 1 - action.abs_diff(ncols / 2)) as f32
+    }
+
+    /// Every column is packed into 9 bits: its height and
+    /// a bit per row set when that cell belongs to 'x'
+    fn hash_key(&self) -> Option<u64> {
+        let (nrows, ncols) = self.shape();
+        let mut hash = 0;
+
+        for col in 0..ncols {
+            let height = (0..nrows)
+                .take_while(|&row| self.grid[row][col] != EMPTY_CELL)
+                .count();
+            let owners = (0..height)
+                .filter(|&row| self.grid[row][col] == 'x')
+                .fold(0, |bits, row| bits | 1 << row);
+
+            hash = hash << 9 | (height as u64) << 6 | owners;
+        }
+
+        Some(hash)
+    }
+}
+
 impl Display for Game {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let (_, ncols) = self.shape();
@@ -162,13 +243,13 @@ impl Display for Game {
 
 #[cfg(test)]
 mod tests {
-    use crate::player::Player;
+    use mcts::State;
 
     use super::Game;
 
     #[test]
     fn test_terminal_board_horizontal() {
-        let mut game = Game::new([Player::Human; 2]);
+        let mut game = Game::new();
 
         for i in 3..7 {
             assert!(!game.is_terminal());
@@ -181,7 +262,7 @@ mod tests {
 
     #[test]
     fn test_terminal_board_vertical() {
-        let mut game = Game::new([Player::Human; 2]);
+        let mut game = Game::new();
 
         for _ in 0..4 {
             assert!(!game.is_terminal());
@@ -194,7 +275,7 @@ mod tests {
 
     #[test]
     fn test_terminal_board_diagonal() {
-        let mut game = Game::new([Player::Human; 2]);
+        let mut game = Game::new();
         let moves = [0, 1, 2, 3, 1, 2, 3, 3, 2, 5, 3];
 
         for col in moves {
@@ -204,4 +285,15 @@ mod tests {
 
         assert!(game.is_terminal());
     }
+
+    #[test]
+    fn test_hash_key_transposition() {
+        let game = Game::new();
+        let a = game.apply_action(0).apply_action(1).apply_action(2);
+        let b = game.apply_action(2).apply_action(1).apply_action(0);
+        let c = game.apply_action(1).apply_action(0).apply_action(2);
+
+        assert_eq!(a.hash_key(), b.hash_key());
+        assert_ne!(a.hash_key(), c.hash_key());
+    }
 }
@@ -1,12 +1,16 @@
 #![allow(dead_code)]
 
+use std::time::Instant;
+
 use game::Game;
+use mcts::{
+    compact::CompactMcts, driver::SearchDriver, observer::SearchInfo, policy::Ucb1, Mcts,
+    SearchLimits,
+};
 use player::Player;
 
 pub mod game;
 pub mod player;
-pub mod search_state;
-pub mod tree;
 
 fn main() {
     let mut args = std::env::args().collect::<Vec<_>>();
@@ -17,18 +21,57 @@ fn main() {
         args.push("1000".to_owned());
     }
 
-    let search_time = args[2].parse::<u128>().unwrap();
+    let search_time = args[2].parse::<u64>().unwrap();
+
+    if args[1] == "bench" {
+        bench(search_time);
+        return;
+    }
 
     let mut players = [Player::Human, Player::Human];
 
     for (player, c) in players.iter_mut().zip(args[1].chars()) {
         *player = if c == 'a' {
-            Player::Ai(search_time)
+            Player::Ai(SearchLimits::millis(search_time))
         } else {
             Player::Human
         };
     }
 
-    let mut game = Game::new(players);
-    game.main_loop(true);
+    let mut game = Game::new();
+    game.main_loop(&players, true);
+}
+
+/// Compare iterations/sec and bytes/node of the default and the
+/// compact tree layout, searching the starting position
+fn bench(search_time: u64) {
+    let limits = SearchLimits::millis(search_time);
+
+    // the compact tree is plain UCB1 without the solver, and neither prints
+    let mut searcher: Mcts<Game> = Mcts::with_policy(Box::new(Ucb1::default()));
+    searcher.set_solver(false);
+    searcher.set_observer(Box::new(|_: &SearchInfo<usize>| {}));
+    let started = Instant::now();
+    searcher.search(&limits, Game::new());
+    let elapsed = started.elapsed().as_secs_f32();
+    let tree = searcher.tree();
+    let default = (
+        tree.nodes[0].num_sims() as f32 / elapsed,
+        tree.memory_usage() / tree.size(),
+    );
+
+    let mut searcher: CompactMcts<Game> = CompactMcts::default();
+    searcher.set_observer(Box::new(|_: &SearchInfo<usize>| {}));
+    let started = Instant::now();
+    searcher.search(&limits, Game::new());
+    let elapsed = started.elapsed().as_secs_f32();
+    let tree = searcher.tree();
+    let compact = (
+        tree.nodes[0].num_sims() as f32 / elapsed,
+        tree.memory_usage() / tree.size(),
+    );
+
+    println!("layout\tits/sec\tbytes/node");
+    println!("default\t{}\t{}", default.0, default.1);
+    println!("compact\t{}\t{}", compact.0, compact.1);
 }
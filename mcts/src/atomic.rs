@@ -0,0 +1,31 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// An `f32` that can be updated from multiple threads at once,
+/// stored as its bit pattern
+#[derive(Default)]
+pub struct AtomicF32(AtomicU32);
+
+impl AtomicF32 {
+    pub fn new(value: f32) -> Self {
+        AtomicF32(AtomicU32::new(value.to_bits()))
+    }
+
+    pub fn load(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn store(&self, value: f32) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn fetch_add(&self, value: f32) -> f32 {
+        let bits = self
+            .0
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f32::from_bits(bits) + value).to_bits())
+            })
+            .unwrap();
+
+        f32::from_bits(bits)
+    }
+}
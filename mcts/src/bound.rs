@@ -0,0 +1,53 @@
+/// Caps the size of a tree. Unlike the node and memory limits in
+/// [`SearchLimits`](crate::SearchLimits), the search keeps running
+/// once the tree is full
+#[derive(Clone, Copy, Debug)]
+pub struct TreeBound {
+    pub max_nodes: Option<usize>,
+    /// Estimated memory in bytes, see [`Tree::memory_usage`](crate::tree::Tree::memory_usage)
+    pub max_memory: Option<usize>,
+    pub on_full: OnFull,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum OnFull {
+    /// Keep the tree as it is, simulating from leaves without expanding them
+    StopExpanding,
+    /// Cut the least visited subtrees until the tree is at `target` times its
+    /// bound, their slots get recycled for new nodes. Falls back to
+    /// [`OnFull::StopExpanding`] while multiple threads share the tree
+    Prune { target: f32 },
+}
+
+impl Default for OnFull {
+    fn default() -> Self {
+        OnFull::Prune { target: 0.9 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OnFull, TreeBound};
+    use crate::{
+        limits::SearchLimits,
+        state::tests::{quiet_mcts, TicTacToe},
+    };
+
+    #[test]
+    fn test_tree_bound_recycles_nodes() {
+        let mut searcher = quiet_mcts();
+        searcher.set_transpositions(true);
+        searcher.set_tree_bound(Some(TreeBound {
+            max_nodes: Some(300),
+            max_memory: None,
+            on_full: OnFull::Prune { target: 0.8 },
+        }));
+        searcher.search(&SearchLimits::iterations(5000), TicTacToe::default());
+
+        let tree = searcher.tree();
+
+        assert_eq!(tree.nodes[0].num_sims(), 5000);
+        assert!(tree.size() <= 300);
+        assert!(tree.nodes.len() <= 300);
+    }
+}
@@ -0,0 +1,410 @@
+use std::{
+    fmt::Display,
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use fastrand::Rng;
+
+use crate::{
+    driver::{ActionTree, Reporter, SearchDriver},
+    final_move::{FinalMoveSelection, SelectedBy},
+    limits::SearchLimits,
+    mcts::Mcts,
+    observer::{SearchInfo, SearchObserver},
+    state::State,
+};
+
+/// Node of a [`CompactTree`]. Instead of its state, a node only stores the
+/// action that leads to it. Its children are the `num_children` nodes
+/// starting at `first_child`
+#[derive(Clone)]
+pub struct CompactNode<A> {
+    /// `None` for the root
+    pub action: Option<A>,
+    pub first_child: u32,
+    pub num_children: u32,
+    num_sims: u32,
+    score: f32,
+}
+
+impl<A> CompactNode<A> {
+    fn new(action: Option<A>) -> Self {
+        CompactNode {
+            action,
+            first_child: 0,
+            num_children: 0,
+            num_sims: 0,
+            score: 0.,
+        }
+    }
+
+    pub fn is_expanded(&self) -> bool {
+        self.num_children > 0
+    }
+
+    pub fn children(&self) -> Range<usize> {
+        let first_child = self.first_child as usize;
+        first_child..first_child + self.num_children as usize
+    }
+
+    pub fn num_sims(&self) -> usize {
+        self.num_sims as usize
+    }
+
+    pub fn score(&self) -> f32 {
+        self.score
+    }
+
+    pub fn mean_score(&self) -> f32 {
+        self.score / self.num_sims as f32
+    }
+
+    fn update(&mut self, reward: f32) {
+        self.num_sims += 1;
+        self.score += reward;
+    }
+}
+
+/// Alternative to [`Tree`](crate::tree::Tree) that trades speed for memory:
+/// only the root state is stored, the states of other nodes are derived by
+/// replaying actions while descending. All children of a node are added at
+/// once, in a contiguous range of the arena.
+///
+/// Selection is plain UCB1, transpositions, the solver, RAVE, chance
+/// events, progressive widening and more than two players aren't supported
+pub struct CompactTree<T>
+where
+    T: State,
+{
+    pub nodes: Vec<CompactNode<T::Action>>,
+    root_state: Option<T>,
+    /// Exploration constant of UCB1
+    pub c: f32,
+    rng: Rng,
+}
+
+impl<T> Default for CompactTree<T>
+where
+    T: State,
+{
+    fn default() -> Self {
+        CompactTree {
+            nodes: vec![],
+            root_state: None,
+            c: 1.,
+            rng: Rng::new(),
+        }
+    }
+}
+
+impl<T> CompactTree<T>
+where
+    T: State + Clone,
+{
+    /// Throw away the tree and start over from `state`. Panics for states
+    /// implementing [`State::current_player`], only two players taking
+    /// turns are supported
+    pub fn set_root(&mut self, state: T) {
+        assert!(
+            state.current_player().is_none(),
+            "only two players taking turns are supported, not states with a current player"
+        );
+
+        self.nodes.clear();
+        self.nodes.push(CompactNode::new(None));
+        self.root_state = Some(state);
+    }
+
+    /// See [`Tree::set_seed`](crate::tree::Tree::set_seed)
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::with_seed(seed);
+    }
+
+    /// A new generator seeded from this tree's generator
+    pub fn fork_rng(&mut self) -> Rng {
+        Rng::with_seed(self.rng.u64(..))
+    }
+
+    /// Descend from the root, returns the path of node ids together
+    /// with the state of the last node
+    pub fn select(&self) -> (Vec<usize>, T) {
+        let mut state = self.root_state.clone().unwrap();
+        let mut path = vec![0];
+        let mut node_id = 0;
+
+        while self.nodes[node_id].is_expanded() {
+            node_id = self.select_child(node_id);
+            state = state.apply_action(self.nodes[node_id].action.clone().unwrap());
+            path.push(node_id);
+        }
+
+        (path, state)
+    }
+
+    /// UCB1, unexplored children come first
+    fn select_child(&self, node_id: usize) -> usize {
+        let parent = &self.nodes[node_id];
+        let log_n = (parent.num_sims as f32).ln();
+        let mut best = node_id;
+        let mut best_score = f32::NEG_INFINITY;
+
+        for child_id in parent.children() {
+            let child = &self.nodes[child_id];
+            if child.num_sims == 0 {
+                return child_id;
+            }
+
+            let n = child.num_sims as f32;
+            let score = child.mean_score() + self.c * (2. * log_n / n).sqrt();
+            if score > best_score {
+                best = child_id;
+                best_score = score;
+            }
+        }
+
+        best
+    }
+
+    /// Add all children of a leaf that was simulated before, moving down
+    /// to its first child. Returns the node to simulate from and its state
+    pub fn expand(&mut self, node_id: usize, mut state: T) -> (usize, T) {
+        if self.nodes[node_id].num_sims == 0 || state.is_terminal(0) {
+            return (node_id, state);
+        }
+
+        let actions = state.possible_actions(&mut self.rng);
+        let first_child = self.nodes.len();
+        if actions.is_empty() {
+            return (node_id, state);
+        }
+
+        let node = &mut self.nodes[node_id];
+        node.first_child = first_child as u32;
+        node.num_children = actions.len() as u32;
+
+        let state = state.apply_action(actions[0].clone());
+        self.nodes.extend(
+            actions
+                .into_iter()
+                .map(|action| CompactNode::new(Some(action))),
+        );
+
+        (first_child, state)
+    }
+
+    /// `reward` is from the perspective of the player who moved
+    /// into the last node of the path
+    pub fn backpropagate(&mut self, path: &[usize], reward: f32) {
+        let mut node_reward = reward;
+        for &id in path.iter().rev() {
+            self.nodes[id].update(node_reward);
+
+            node_reward = 1. - node_reward;
+        }
+    }
+
+    /// The most visited action at the root, along with the criterion that
+    /// chose it. `None` when the root state has no actions
+    pub fn final_action(&self) -> Option<(T::Action, SelectedBy)> {
+        let root_state = self.root_state.as_ref().unwrap();
+        self.select_root_action(FinalMoveSelection::RobustChild, root_state, &self.rng)
+    }
+
+    /// Panics when the root state has no actions
+    pub fn best_action(&self) -> T::Action {
+        self.final_action()
+            .expect("the root state has no actions")
+            .0
+    }
+}
+
+impl<T> ActionTree for CompactTree<T>
+where
+    T: State,
+{
+    type Action = T::Action;
+
+    fn children(&self, node_id: usize) -> impl Iterator<Item = usize> + '_ {
+        self.nodes[node_id].children()
+    }
+
+    fn action(&self, node_id: usize) -> &T::Action {
+        self.nodes[node_id].action.as_ref().unwrap()
+    }
+
+    fn num_sims(&self, node_id: usize) -> usize {
+        self.nodes[node_id].num_sims()
+    }
+
+    fn mean_score(&self, node_id: usize) -> f32 {
+        self.nodes[node_id].mean_score()
+    }
+}
+
+impl<T> SearchDriver for CompactTree<T>
+where
+    T: State + Clone,
+    T::Action: Display,
+{
+    type Action = T::Action;
+
+    fn iterate(&mut self) {
+        let (mut path, state) = self.select();
+        let node_id = *path.last().unwrap();
+        let (child_id, state) = self.expand(node_id, state);
+        if child_id != node_id {
+            path.push(child_id);
+        }
+
+        let reward = Mcts::rollout(&state, &mut self.rng).two_player();
+        self.backpropagate(&path, reward);
+    }
+
+    fn size(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Memory used by the nodes and the root state in bytes,
+    /// comparable to [`Tree::memory_usage`](crate::tree::Tree::memory_usage)
+    fn memory_usage(&self) -> usize {
+        self.nodes.len() * std::mem::size_of::<CompactNode<T::Action>>() + std::mem::size_of::<T>()
+    }
+
+    fn search_info(&self, iterations: usize, elapsed: Duration) -> SearchInfo<T::Action> {
+        let selected = self.final_action().filter(|_| self.nodes[0].is_expanded());
+
+        SearchInfo {
+            iterations,
+            elapsed,
+            nodes: self.size(),
+            memory: self.memory_usage(),
+            ..self.root_info(selected)
+        }
+    }
+}
+
+/// Searcher using a [`CompactTree`], every search starts from scratch
+pub struct CompactMcts<T>
+where
+    T: State,
+{
+    tree: CompactTree<T>,
+    reporter: Reporter<T::Action>,
+}
+
+impl<T> Default for CompactMcts<T>
+where
+    T: State,
+{
+    fn default() -> Self {
+        CompactMcts {
+            tree: CompactTree::default(),
+            reporter: Reporter::default(),
+        }
+    }
+}
+
+impl<T> CompactMcts<T>
+where
+    T: State + Clone,
+    T::Action: Display,
+{
+    pub fn tree(&self) -> &CompactTree<T> {
+        &self.tree
+    }
+
+    /// See [`Mcts::set_seed`]
+    pub fn set_seed(&mut self, seed: u64) {
+        self.tree.set_seed(seed);
+    }
+
+    /// See [`Mcts::set_observer`]
+    pub fn set_observer(&mut self, observer: Box<dyn SearchObserver<T::Action>>) {
+        self.reporter.set_observer(observer);
+    }
+
+    /// See [`Mcts::set_report_interval`]
+    pub fn set_report_interval(&mut self, interval: Option<Duration>) {
+        self.reporter.set_interval(interval);
+    }
+
+    pub fn search(&mut self, limits: &SearchLimits, root_state: T) -> T::Action {
+        let started = Instant::now();
+        self.tree.set_root(root_state);
+
+        let iterations = self.tree.drive(limits, started, &mut self.reporter);
+        self.reporter
+            .finish(self.tree.search_info(iterations, started.elapsed()));
+
+        self.tree.best_action()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CompactMcts, CompactTree};
+    use crate::{
+        final_move::SelectedBy,
+        limits::SearchLimits,
+        state::tests::{quiet, TicTacToe, Turns},
+    };
+
+    #[test]
+    fn test_compact_tree_adds_children_at_once() {
+        let mut searcher = quiet(CompactMcts::default());
+        searcher.set_seed(1);
+        searcher.search(&SearchLimits::iterations(500), TicTacToe::default());
+
+        let tree = searcher.tree();
+        let mut expanded = (0..tree.nodes.len())
+            .filter(|&node_id| tree.nodes[node_id].is_expanded())
+            .collect::<Vec<_>>();
+        expanded.sort_by_key(|&node_id| tree.nodes[node_id].first_child);
+
+        // the children of every expanded node follow each other, the
+        // ranges of all nodes covering the arena after the root
+        let mut next = 1;
+        for &node_id in &expanded {
+            let node = &tree.nodes[node_id];
+            assert_eq!(node.children().start, next);
+            // expanded on the visit after the one that simulated the leaf
+            assert!(node.num_sims() >= 2);
+            next = node.children().end;
+        }
+        assert_eq!(next, tree.nodes.len());
+
+        assert_eq!(tree.nodes[0].num_children, 9);
+        assert_eq!(tree.nodes[0].num_sims(), 500);
+        let child_sims = tree.nodes[0]
+            .children()
+            .map(|child_id| tree.nodes[child_id].num_sims())
+            .sum::<usize>();
+        assert_eq!(child_sims, 500 - 1);
+    }
+
+    #[test]
+    fn test_compact_tree_falls_back_to_prior() {
+        let mut searcher = quiet(CompactMcts::default());
+
+        // the centre has the highest prior
+        let limits = SearchLimits::iterations(0);
+        assert_eq!(searcher.search(&limits, TicTacToe::default()), 4);
+        assert_eq!(searcher.tree().final_action().unwrap().1, SelectedBy::Prior);
+
+        // a finished game has nothing to choose from
+        let mut tree = CompactTree::default();
+        tree.set_root(TicTacToe::play(&[0, 3, 1, 4, 2]));
+        assert_eq!(tree.final_action(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "only two players taking turns are supported")]
+    fn test_compact_tree_rejects_current_player() {
+        let mut searcher = quiet(CompactMcts::default());
+        searcher.search(
+            &SearchLimits::iterations(10),
+            Turns(TicTacToe::win_in_one()),
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use fastrand::Rng;
+
+use crate::{
+    final_move::{FinalMoveSelection, SelectedBy},
+    limits::SearchLimits,
+    observer::{ChildInfo, ConsoleObserver, SearchInfo, SearchObserver},
+    state::State,
+};
+
+/// Sends the reports of a search to its observer, periodically
+/// while searching and once when the search finishes
+pub struct Reporter<A> {
+    /// Prints to the console when not set
+    observer: Option<Box<dyn SearchObserver<A>>>,
+    interval: Option<Duration>,
+    last_report: Instant,
+}
+
+impl<A> Default for Reporter<A> {
+    fn default() -> Self {
+        Reporter {
+            observer: None,
+            interval: None,
+            last_report: Instant::now(),
+        }
+    }
+}
+
+impl<A> Reporter<A> {
+    pub fn set_observer(&mut self, observer: Box<dyn SearchObserver<A>>) {
+        self.observer = Some(observer);
+    }
+
+    /// By default the observer is only notified when the search finishes
+    pub fn set_interval(&mut self, interval: Option<Duration>) {
+        self.interval = interval;
+    }
+
+    pub fn interval(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    /// The first periodic report is due one interval after `started`
+    pub fn start(&mut self, started: Instant) {
+        self.last_report = started;
+    }
+}
+
+impl<A> Reporter<A>
+where
+    A: Display,
+{
+    fn observer(&mut self) -> &mut dyn SearchObserver<A> {
+        self.observer
+            .get_or_insert_with(|| Box::new(ConsoleObserver))
+            .as_mut()
+    }
+
+    /// Report the snapshot built by `info` when the interval has passed
+    pub fn report(&mut self, info: impl FnOnce() -> SearchInfo<A>) {
+        if self
+            .interval
+            .is_some_and(|interval| self.last_report.elapsed() >= interval)
+        {
+            self.observer().on_info(&info());
+            self.last_report = Instant::now();
+        }
+    }
+
+    /// Hand the final report of a search to the observer
+    pub fn finish(&mut self, mut info: SearchInfo<A>) {
+        info.finished = true;
+        self.observer().on_info(&info);
+    }
+}
+
+/// A search running one iteration at a time until its limits are reached
+pub trait SearchDriver {
+    type Action: Display;
+
+    fn iterate(&mut self);
+
+    /// Number of nodes, for [`SearchLimits::nodes`]
+    fn size(&self) -> usize;
+
+    /// Estimate of the memory used in bytes, for [`SearchLimits::memory`]
+    fn memory_usage(&self) -> usize;
+
+    /// Snapshot of the search, not marked as finished
+    fn search_info(&self, iterations: usize, elapsed: Duration) -> SearchInfo<Self::Action>;
+
+    /// Iterate until one of the limits is reached, handing periodic
+    /// reports to `reporter`. Returns the number of iterations
+    fn drive(
+        &mut self,
+        limits: &SearchLimits,
+        started: Instant,
+        reporter: &mut Reporter<Self::Action>,
+    ) -> usize {
+        reporter.start(started);
+        let mut iterations = 0;
+
+        while !limits.is_reached_with(started, iterations, self.size(), self.memory_usage()) {
+            reporter.report(|| self.search_info(iterations, started.elapsed()));
+            self.iterate();
+            iterations += 1;
+        }
+
+        iterations
+    }
+}
+
+/// Trees whose nodes hold the action leading to them rather than a state,
+/// rooted at node 0
+pub trait ActionTree {
+    type Action: Clone + PartialEq;
+
+    fn children(&self, node_id: usize) -> impl Iterator<Item = usize> + '_;
+
+    /// The action leading to a node other than the root
+    fn action(&self, node_id: usize) -> &Self::Action;
+
+    fn num_sims(&self, node_id: usize) -> usize;
+
+    fn mean_score(&self, node_id: usize) -> f32;
+
+    /// Not every tree keeps track of the variance
+    fn std_dev(&self, _node_id: usize) -> Option<f32> {
+        None
+    }
+
+    fn most_visited_child(&self, node_id: usize) -> Option<usize> {
+        self.children(node_id)
+            .max_by_key(|&child_id| self.num_sims(child_id))
+    }
+
+    /// Most visited line from the root
+    fn continuation(&self) -> Vec<Self::Action> {
+        let mut actions = vec![];
+        let mut node_id = 0;
+
+        while let Some(child_id) = self.most_visited_child(node_id) {
+            actions.push(self.action(child_id).clone());
+            node_id = child_id;
+        }
+
+        actions
+    }
+
+    /// The root action chosen by `selection`, along with the criterion that
+    /// chose it. Before the first iteration, the action of `root_state` with
+    /// the highest prior. `None` when the root has no actions
+    fn select_root_action<T>(
+        &self,
+        selection: FinalMoveSelection,
+        root_state: &T,
+        rng: &Rng,
+    ) -> Option<(Self::Action, SelectedBy)>
+    where
+        T: State<Action = Self::Action>,
+    {
+        let child_ids = self.children(0).collect::<Vec<_>>();
+        let stats = child_ids
+            .iter()
+            .map(|&child_id| (self.num_sims(child_id), self.mean_score(child_id)))
+            .collect::<Vec<_>>();
+
+        if let Some((i, selected_by)) = selection.select(&stats) {
+            return Some((self.action(child_ids[i]).clone(), selected_by));
+        }
+
+        // ties keep the shuffled order, like the untried actions of a node
+        let action = root_state
+            .possible_actions(&mut rng.clone())
+            .into_iter()
+            .max_by(|a, b| {
+                root_state
+                    .action_prior(a)
+                    .total_cmp(&root_state.action_prior(b))
+            })?;
+
+        Some((action, SelectedBy::Prior))
+    }
+
+    /// Snapshot of the root's children and the principal variation, given
+    /// the final action if there is one. Counters are left at zero
+    fn root_info(&self, selected: Option<(Self::Action, SelectedBy)>) -> SearchInfo<Self::Action> {
+        let children = self
+            .children(0)
+            .map(|child_id| ChildInfo {
+                action: self.action(child_id).clone(),
+                num_sims: self.num_sims(child_id),
+                mean_score: self.mean_score(child_id),
+                std_dev: self.std_dev(child_id),
+                proof: None,
+            })
+            .collect::<Vec<_>>();
+
+        let (best_action, selected_by) = selected.unzip();
+        let value = self
+            .children(0)
+            .find(|&child_id| Some(self.action(child_id)) == best_action.as_ref())
+            .map(|child_id| self.mean_score(child_id));
+        let pv = self.continuation();
+
+        SearchInfo {
+            iterations: 0,
+            elapsed: Duration::ZERO,
+            nodes: 0,
+            memory: 0,
+            depth: pv.len(),
+            best_action,
+            value,
+            proof: None,
+            pv,
+            children,
+            selected_by,
+            thread_iterations: vec![],
+            finished: false,
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::fmt::Display;
+
+/// How to choose the action to play once the search is done.
+/// Proven wins are always played and proven losses avoided,
+/// whatever the selection
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FinalMoveSelection {
+    /// Highest mean score
+    MaxChild,
+    /// Most simulations
+    #[default]
+    RobustChild,
+    /// The child with both the highest mean score and the most simulations.
+    /// When they differ, the search continues for up to `extension` times
+    /// its iterations until they agree, falling back to the robust child
+    MaxRobustChild { extension: f32 },
+    /// Highest lower confidence bound, `mean - a / sqrt(n)`
+    SecureChild { a: f32 },
+}
+
+/// The criterion that actually chose the final action
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SelectedBy {
+    /// A proven win, or the slowest loss when every action loses
+    Proof,
+    MaxChild,
+    RobustChild,
+    MaxRobustChild,
+    SecureChild,
+    /// The last action left by sequential halving, see
+    /// [`GumbelRoot`](crate::gumbel::GumbelRoot)
+    SequentialHalving,
+    /// Nothing was simulated yet, the untried action with the highest prior
+    Prior,
+}
+
+impl FinalMoveSelection {
+    /// Index of the chosen child, given the simulations and mean score of
+    /// every candidate. Children without simulations can only be chosen
+    /// as the robust child
+    pub fn select(self, children: &[(usize, f32)]) -> Option<(usize, SelectedBy)> {
+        let robust = (0..children.len())
+            .max_by_key(|&i| children[i].0)
+            .map(|i| (i, SelectedBy::RobustChild));
+        let best_explored = |value: &dyn Fn(usize, f32) -> f32| {
+            (0..children.len())
+                .filter(|&i| children[i].0 > 0)
+                .max_by(|&i, &j| {
+                    let (x, y) = (children[i], children[j]);
+                    value(x.0, x.1).total_cmp(&value(y.0, y.1))
+                })
+        };
+
+        match self {
+            FinalMoveSelection::MaxChild => best_explored(&|_, mean| mean)
+                .map(|i| (i, SelectedBy::MaxChild))
+                .or(robust),
+            FinalMoveSelection::RobustChild => robust,
+            FinalMoveSelection::MaxRobustChild { .. } => {
+                let max = best_explored(&|_, mean| mean);
+                match robust {
+                    Some((i, _)) if max == Some(i) => Some((i, SelectedBy::MaxRobustChild)),
+                    _ => robust,
+                }
+            }
+            FinalMoveSelection::SecureChild { a } => {
+                best_explored(&|n, mean| mean - a / (n as f32).sqrt())
+                    .map(|i| (i, SelectedBy::SecureChild))
+                    .or(robust)
+            }
+        }
+    }
+}
+
+impl Display for SelectedBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SelectedBy::Proof => "proof",
+            SelectedBy::MaxChild => "max child",
+            SelectedBy::RobustChild => "robust child",
+            SelectedBy::MaxRobustChild => "max-robust child",
+            SelectedBy::SecureChild => "secure child",
+            SelectedBy::SequentialHalving => "sequential halving",
+            SelectedBy::Prior => "prior",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FinalMoveSelection, SelectedBy};
+
+    #[test]
+    fn test_final_move_criteria() {
+        // (simulations, mean score)
+        let children = [(100, 0.6), (3, 0.9), (0, 0.), (60, 0.65)];
+
+        let select = |selection: FinalMoveSelection| selection.select(&children).unwrap();
+
+        assert_eq!(
+            select(FinalMoveSelection::MaxChild),
+            (1, SelectedBy::MaxChild)
+        );
+        assert_eq!(
+            select(FinalMoveSelection::RobustChild),
+            (0, SelectedBy::RobustChild)
+        );
+        assert_eq!(
+            select(FinalMoveSelection::MaxRobustChild { extension: 1. }),
+            (0, SelectedBy::RobustChild)
+        );
+        assert_eq!(
+            select(FinalMoveSelection::SecureChild { a: 1. }),
+            (3, SelectedBy::SecureChild)
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use fastrand::Rng;
+
+/// Root strategy for small budgets, following Gumbel AlphaZero. Instead of
+/// UCT, the root samples `num_sampled` actions without replacement by
+/// adding Gumbel noise to the logits of their priors, then splits the
+/// iterations over a few rounds of sequential halving: every remaining
+/// action gets an equal share of the round, after which the worse half is
+/// dropped. Nodes below the root still use the tree's selection policy
+#[derive(Clone, Copy, Debug)]
+pub struct GumbelRoot {
+    /// Number of root actions considered
+    pub num_sampled: usize,
+    /// Scale of the mean scores against the logits,
+    /// see [`GumbelRoot::transform`]
+    pub c_visit: f32,
+    pub c_scale: f32,
+}
+
+impl Default for GumbelRoot {
+    fn default() -> Self {
+        GumbelRoot {
+            num_sampled: 16,
+            c_visit: 50.,
+            c_scale: 1.,
+        }
+    }
+}
+
+impl GumbelRoot {
+    /// Monotonic transformation of a mean score in `[0, 1]`, growing
+    /// with the simulations of the most visited root action
+    pub fn transform(&self, score: f32, max_sims: usize) -> f32 {
+        (self.c_visit + max_sims as f32) * self.c_scale * score
+    }
+
+    /// Policy improvement target over the root actions, given their priors,
+    /// simulations and mean scores: the softmax of the prior logits plus the
+    /// transformed scores. Unvisited actions are completed with the prior
+    /// weighted mean score of the visited ones
+    pub fn improved_policy(&self, priors: &[f32], num_sims: &[usize], scores: &[f32]) -> Vec<f32> {
+        let max_sims = num_sims.iter().copied().max().unwrap_or(0);
+        let visited_prior = (0..priors.len())
+            .filter(|&i| num_sims[i] > 0)
+            .map(|i| priors[i])
+            .sum::<f32>();
+        let mixed_score = match visited_prior > 0. {
+            true => {
+                (0..priors.len())
+                    .filter(|&i| num_sims[i] > 0)
+                    .map(|i| priors[i] * scores[i])
+                    .sum::<f32>()
+                    / visited_prior
+            }
+            false => 0.5,
+        };
+
+        let logits = (0..priors.len())
+            .map(|i| {
+                let score = if num_sims[i] > 0 {
+                    scores[i]
+                } else {
+                    mixed_score
+                };
+                logit(priors[i]) + self.transform(score, max_sims)
+            })
+            .collect::<Vec<_>>();
+
+        let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let exps = logits.iter().map(|x| (x - max).exp()).collect::<Vec<_>>();
+        let total = exps.iter().sum::<f32>();
+
+        exps.into_iter().map(|x| x / total).collect()
+    }
+}
+
+pub(crate) fn logit(prior: f32) -> f32 {
+    prior.max(f32::MIN_POSITIVE).ln()
+}
+
+/// Sample from the standard Gumbel distribution
+pub(crate) fn gumbel(rng: &mut Rng) -> f32 {
+    let u = rng.f32().max(f32::MIN_POSITIVE);
+    -(-u.ln()).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    use super::GumbelRoot;
+    use crate::{
+        final_move::SelectedBy,
+        limits::SearchLimits,
+        observer::SearchInfo,
+        state::tests::{quiet_mcts, TicTacToe},
+        Mcts,
+    };
+
+    #[test]
+    fn test_improved_policy() {
+        let gumbel = GumbelRoot::default();
+        let policy = gumbel.improved_policy(&[0.5, 0.3, 0.2], &[10, 10, 0], &[0.4, 0.6, 0.]);
+
+        assert!((policy.iter().sum::<f32>() - 1.).abs() < 1e-5);
+        // the better score outweighs the higher prior
+        assert!(policy[1] > policy[0]);
+        // completed with the mixed score, 0.475
+        assert!(policy[2] > policy[0] && policy[2] < policy[1]);
+    }
+
+    #[test]
+    fn test_gumbel_root_finds_win() {
+        let infos: Arc<Mutex<Vec<SearchInfo<usize>>>> = Arc::default();
+        let mut searcher = Mcts::default();
+        let observer_infos = infos.clone();
+        searcher.set_observer(Box::new(move |info: &SearchInfo<usize>| {
+            observer_infos.lock().unwrap().push(info.clone())
+        }));
+        searcher.set_seed(1);
+        // keep the immediate win from ending the search
+        searcher.set_solver(false);
+        searcher.set_gumbel_root(Some(GumbelRoot::default()));
+
+        assert_eq!(
+            searcher.search(&SearchLimits::iterations(210), TicTacToe::win_in_one()),
+            2
+        );
+
+        let infos = infos.lock().unwrap();
+        let info = infos.last().unwrap();
+        assert_eq!(info.selected_by, Some(SelectedBy::SequentialHalving));
+        assert!(info.iterations <= 210);
+        assert_eq!(searcher.tree().nodes[0].num_sims(), info.iterations);
+
+        let target = searcher.policy_target();
+        assert_eq!(target.len(), 5);
+        assert!((target.iter().map(|(_, p)| p).sum::<f32>() - 1.).abs() < 1e-5);
+        let (best, _) = target.iter().max_by(|a, b| a.1.total_cmp(&b.1)).unwrap();
+        assert_eq!(*best, 2);
+    }
+
+    #[test]
+    fn test_gumbel_root_round_shares() {
+        let mut searcher = quiet_mcts();
+        searcher.set_seed(1);
+        searcher.set_solver(false);
+        searcher.set_gumbel_root(Some(GumbelRoot::default()));
+        searcher.search(&SearchLimits::iterations(210), TicTacToe::win_in_one());
+
+        // 3 rounds of 70 iterations over the 5, 3 and 2 remaining actions
+        let tree = searcher.tree();
+        let mut num_sims = tree.nodes[0]
+            .child_ids
+            .iter()
+            .map(|&child_id| tree.nodes[child_id].num_sims())
+            .collect::<Vec<_>>();
+        num_sims.sort();
+        assert_eq!(num_sims, vec![14, 14, 14 + 23, 14 + 23 + 35, 14 + 23 + 35]);
+    }
+
+    #[test]
+    fn test_gumbel_root_time_limit() {
+        let mut searcher = quiet_mcts();
+        searcher.set_seed(1);
+        searcher.set_solver(false);
+        searcher.set_gumbel_root(Some(GumbelRoot::default()));
+
+        let started = Instant::now();
+        assert_eq!(
+            searcher.search(&SearchLimits::millis(50), TicTacToe::win_in_one()),
+            2
+        );
+        // only checks that the rounds end, timing is up to the machine
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,159 @@
+use fastrand::Rng;
+
+use crate::{node::Proof, state::State};
+
+/// Shallow minimax searches for forced wins and losses, to keep the search
+/// out of short tactical traps it samples too rarely to notice. Each of
+/// them is enabled by giving its depth in plies. Proofs found at selection
+/// and expansion are propagated by the solver, see
+/// [`Tree::set_solver`](crate::tree::Tree::set_solver)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HybridMinimax {
+    /// MCTS-MR: every simulation move plays a win found within this depth,
+    /// and avoids moves that lose within it whenever possible
+    pub rollout_depth: Option<usize>,
+    /// MCTS-MS: search a node to this depth once selection reaches it
+    /// with the given number of simulations
+    pub selection: Option<(usize, usize)>,
+    /// MCTS-MB: search every node to this depth when it's added
+    pub expansion_depth: Option<usize>,
+}
+
+impl HybridMinimax {
+    pub fn rollouts(depth: usize) -> Self {
+        HybridMinimax {
+            rollout_depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    pub fn selection(depth: usize, visits: usize) -> Self {
+        HybridMinimax {
+            selection: Some((depth, visits)),
+            ..Default::default()
+        }
+    }
+
+    pub fn expansion(depth: usize) -> Self {
+        HybridMinimax {
+            expansion_depth: Some(depth),
+            ..Default::default()
+        }
+    }
+}
+
+/// Alpha-beta search over game results, returns the result of `state`
+/// from the perspective of the player who moved into it when it's decided
+/// within `depth` plies. Only two players taking turns without chance
+/// events are searched
+pub fn solve<T>(mut state: T, depth: usize, rng: &mut Rng) -> Option<Proof>
+where
+    T: State,
+{
+    if state.is_terminal(0) {
+        return Some(Proof::from_reward(state.reward(&state)));
+    }
+
+    solve_moves(&state, depth, rng)
+}
+
+/// [`solve`] for a state that isn't terminal
+pub fn solve_moves<T>(state: &T, depth: usize, rng: &mut Rng) -> Option<Proof>
+where
+    T: State,
+{
+    if depth == 0 || state.current_player().is_some() || state.chance_outcomes().is_some() {
+        return None;
+    }
+
+    let mut slowest_loss = 0;
+    let mut has_draw = false;
+    let mut all_proven = true;
+
+    for action in state.possible_actions(rng) {
+        // from the perspective of the player to move in `state`
+        match solve(state.apply_action(action), depth - 1, rng) {
+            Some(Proof::Win(plies)) => return Some(Proof::Loss(plies + 1)),
+            Some(Proof::Loss(plies)) => slowest_loss = slowest_loss.max(plies),
+            Some(Proof::Draw) => has_draw = true,
+            None => all_proven = false,
+        }
+    }
+
+    match (all_proven, has_draw) {
+        (false, _) => None,
+        (true, true) => Some(Proof::Draw),
+        (true, false) => Some(Proof::Win(slowest_loss + 1)),
+    }
+}
+
+/// Simulation move for MCTS-MR: a win within `depth` plies if there is one,
+/// otherwise a random move that doesn't lose within it, if possible
+pub fn rollout_action<T>(state: &T, depth: usize, rng: &mut Rng) -> T::Action
+where
+    T: State,
+{
+    let mut actions = state.possible_actions(rng);
+    let mut safe = vec![];
+
+    for (i, action) in actions.iter().enumerate() {
+        match solve(
+            state.apply_action(action.clone()),
+            depth.saturating_sub(1),
+            rng,
+        ) {
+            Some(Proof::Win(_)) => return action.clone(),
+            Some(Proof::Loss(_)) => {}
+            _ => safe.push(i),
+        }
+    }
+
+    match safe.last() {
+        Some(&i) => actions.swap_remove(i),
+        None => actions.pop().unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fastrand::Rng;
+
+    use super::{rollout_action, HybridMinimax};
+    use crate::{
+        limits::SearchLimits,
+        node::Proof,
+        state::tests::{quiet_mcts, TicTacToe},
+    };
+
+    #[test]
+    fn test_hybrid_minimax_avoids_trap() {
+        // 'o' threatens to complete the middle row
+        let game = TicTacToe::play(&[0, 4, 8, 3]);
+
+        let mut rng = Rng::with_seed(0);
+        for _ in 0..10 {
+            assert_eq!(rollout_action(&game, 2, &mut rng), 5);
+        }
+
+        for (hybrid, iterations) in [
+            (HybridMinimax::expansion(2), 5),
+            (HybridMinimax::selection(2, 1), 30),
+        ] {
+            let mut searcher = quiet_mcts();
+            searcher.set_hybrid_minimax(hybrid);
+
+            assert_eq!(
+                searcher.search(&SearchLimits::iterations(iterations), game),
+                5
+            );
+
+            let tree = searcher.tree();
+            let losses = tree.nodes[0]
+                .child_ids
+                .iter()
+                .filter(|&&child_id| matches!(tree.nodes[child_id].proof(), Some(Proof::Loss(1))))
+                .count();
+            assert_eq!(losses, 4);
+        }
+    }
+}
@@ -0,0 +1,628 @@
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use fastrand::Rng;
+
+use crate::{
+    driver::{ActionTree, Reporter, SearchDriver},
+    final_move::{FinalMoveSelection, SelectedBy},
+    limits::SearchLimits,
+    mcts::Mcts,
+    observer::{SearchInfo, SearchObserver},
+    open_loop::select_available,
+    state::{sample_weighted, Reward, State},
+};
+
+/// States with hidden information. Searching them requires
+/// [`State::current_player`] and [`State::rewards`]
+pub trait Determinize: State + Sized {
+    /// A random state consistent with everything `observer` knows,
+    /// e.g. with the unseen cards dealt at random
+    fn determinize(&self, observer: usize, rng: &mut Rng) -> Self;
+
+    /// Number of players, [`IsmctsVariant::MultipleObserver`] keeps a tree for each
+    fn num_players(&self) -> usize {
+        2
+    }
+
+    /// How `observer` sees an action played in this state, e.g. an opponent
+    /// playing a card face down. Only used by
+    /// [`IsmctsVariant::MultipleObserver`], by default actions are public
+    fn observed_action(&self, action: &Self::Action, _observer: usize) -> Self::Action {
+        action.clone()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum IsmctsVariant {
+    /// A single tree, in which every player sees the actions of the others
+    #[default]
+    SingleObserver,
+    /// A tree per player, holding the actions as that player observes them
+    MultipleObserver,
+}
+
+/// Node of an [`IsmctsTree`], standing for an information set
+pub struct IsmctsNode<A> {
+    /// `None` for the root
+    pub action: Option<A>,
+    /// The player who moved into this node
+    pub player: Option<usize>,
+    pub child_ids: Vec<usize>,
+    num_sims: usize,
+    score: f32,
+    /// Number of times this node's action was legal when its parent was
+    /// visited, see [`OpenLoopTree`](crate::open_loop::OpenLoopTree)
+    availability: usize,
+}
+
+impl<A> IsmctsNode<A> {
+    fn new(action: Option<A>, player: Option<usize>) -> Self {
+        IsmctsNode {
+            action,
+            player,
+            child_ids: vec![],
+            num_sims: 0,
+            score: 0.,
+            availability: 0,
+        }
+    }
+
+    pub fn num_sims(&self) -> usize {
+        self.num_sims
+    }
+
+    pub fn mean_score(&self) -> f32 {
+        self.score / self.num_sims as f32
+    }
+
+    pub fn availability(&self) -> usize {
+        self.availability
+    }
+}
+
+/// Tree of information sets, as seen by one or all players
+pub struct IsmctsTree<A> {
+    pub nodes: Vec<IsmctsNode<A>>,
+}
+
+impl<A> IsmctsTree<A>
+where
+    A: Clone + Eq,
+{
+    fn new() -> Self {
+        IsmctsTree {
+            nodes: vec![IsmctsNode::new(None, None)],
+        }
+    }
+
+    pub fn find_child(&self, node_id: usize, action: &A) -> Option<usize> {
+        self.nodes[node_id]
+            .child_ids
+            .iter()
+            .copied()
+            .find(|&child_id| self.nodes[child_id].action.as_ref() == Some(action))
+    }
+
+    fn find_or_add_child(&mut self, node_id: usize, action: A, player: usize) -> usize {
+        if let Some(child_id) = self.find_child(node_id, &action) {
+            return child_id;
+        }
+
+        let child_id = self.nodes.len();
+        self.nodes.push(IsmctsNode::new(Some(action), Some(player)));
+        self.nodes[node_id].child_ids.push(child_id);
+
+        child_id
+    }
+
+    /// Every node is scored for the player who moved into it
+    fn backpropagate(&mut self, path: &[usize], rewards: &[f32]) {
+        for &id in path {
+            let node = &mut self.nodes[id];
+            node.num_sims += 1;
+            node.score += node.player.map_or(0., |player| rewards[player]);
+        }
+    }
+}
+
+impl<A> ActionTree for IsmctsTree<A>
+where
+    A: Clone + PartialEq,
+{
+    type Action = A;
+
+    fn children(&self, node_id: usize) -> impl Iterator<Item = usize> + '_ {
+        self.nodes[node_id].child_ids.iter().copied()
+    }
+
+    fn action(&self, node_id: usize) -> &A {
+        self.nodes[node_id].action.as_ref().unwrap()
+    }
+
+    fn num_sims(&self, node_id: usize) -> usize {
+        self.nodes[node_id].num_sims()
+    }
+
+    fn mean_score(&self, node_id: usize) -> f32 {
+        self.nodes[node_id].mean_score()
+    }
+}
+
+/// Information Set MCTS: every iteration searches a new determinization of
+/// the root, consistent with what the player to move knows. Nodes are
+/// information sets, identified by the actions leading to them, and
+/// selection is UCB1 over the actions legal in the current determinization.
+/// Every search starts from scratch
+pub struct Ismcts<T>
+where
+    T: Determinize,
+{
+    /// A single tree, or one per player
+    trees: Vec<IsmctsTree<T::Action>>,
+    /// The tree of the player to move at the root
+    root_tree: usize,
+    root_state: Option<T>,
+    /// The player to move at the root, whose view is determinized
+    root_player: usize,
+    variant: IsmctsVariant,
+    /// Exploration constant of UCB1
+    pub c: f32,
+    final_move: FinalMoveSelection,
+    rng: Rng,
+    reporter: Reporter<T::Action>,
+}
+
+impl<T> Default for Ismcts<T>
+where
+    T: Determinize,
+{
+    fn default() -> Self {
+        Ismcts {
+            trees: vec![],
+            root_tree: 0,
+            root_state: None,
+            root_player: 0,
+            variant: IsmctsVariant::default(),
+            c: 1.,
+            final_move: FinalMoveSelection::default(),
+            rng: Rng::new(),
+            reporter: Reporter::default(),
+        }
+    }
+}
+
+impl<T> Ismcts<T>
+where
+    T: Determinize + Clone,
+    T::Action: Display,
+{
+    pub fn new(variant: IsmctsVariant) -> Self {
+        Ismcts {
+            variant,
+            ..Default::default()
+        }
+    }
+
+    /// The tree of the player to move at the root
+    pub fn tree(&self) -> &IsmctsTree<T::Action> {
+        &self.trees[self.root_tree]
+    }
+
+    /// See [`Mcts::set_seed`]
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::with_seed(seed);
+    }
+
+    /// See [`FinalMoveSelection`], the max-robust child isn't extended
+    pub fn set_final_move_selection(&mut self, selection: FinalMoveSelection) {
+        self.final_move = selection;
+    }
+
+    /// See [`Mcts::set_observer`]
+    pub fn set_observer(&mut self, observer: Box<dyn SearchObserver<T::Action>>) {
+        self.reporter.set_observer(observer);
+    }
+
+    /// See [`Mcts::set_report_interval`]
+    pub fn set_report_interval(&mut self, interval: Option<Duration>) {
+        self.reporter.set_interval(interval);
+    }
+
+    /// Search from `root_state` as its player to move sees it,
+    /// anything hidden from that player is never looked at
+    pub fn search(&mut self, limits: &SearchLimits, root_state: T) -> T::Action {
+        let started = Instant::now();
+        let root_player = root_state
+            .current_player()
+            .expect("information set search requires the player to move");
+        let num_trees = match self.variant {
+            IsmctsVariant::SingleObserver => 1,
+            IsmctsVariant::MultipleObserver => root_state.num_players(),
+        };
+
+        self.trees = (0..num_trees).map(|_| IsmctsTree::new()).collect();
+        self.root_tree = match self.variant {
+            IsmctsVariant::SingleObserver => 0,
+            IsmctsVariant::MultipleObserver => root_player,
+        };
+        self.root_state = Some(root_state);
+        self.root_player = root_player;
+
+        // the reporter is handed to the driver next to the searcher itself
+        let mut reporter = std::mem::take(&mut self.reporter);
+        let iterations = self.drive(limits, started, &mut reporter);
+        reporter.finish(self.search_info(iterations, started.elapsed()));
+        self.reporter = reporter;
+
+        self.final_action()
+            .expect("the root state has no actions")
+            .0
+    }
+
+    /// Descend all trees along a determinization, expanding the first
+    /// untried action of the player to move, and backpropagate a rollout
+    fn iterate_determinization(&mut self, mut state: T) {
+        let mut paths = vec![vec![0]; self.trees.len()];
+
+        while !state.is_terminal(0) {
+            if let Some(mut outcomes) = state.chance_outcomes() {
+                let i = sample_weighted(outcomes.iter().map(|(_, p)| *p), &mut self.rng);
+                state = state.apply_action_with(outcomes.swap_remove(i).0, &mut self.rng);
+                continue;
+            }
+
+            let player = state.current_player().unwrap();
+            let mover_tree = match self.variant {
+                IsmctsVariant::SingleObserver => 0,
+                IsmctsVariant::MultipleObserver => player,
+            };
+            let (action, expanded) = self.select_action(&state, mover_tree, &paths[mover_tree]);
+
+            for (i, path) in paths.iter_mut().enumerate() {
+                let observed = match self.variant {
+                    IsmctsVariant::SingleObserver => action.clone(),
+                    IsmctsVariant::MultipleObserver => state.observed_action(&action, i),
+                };
+                let node_id = *path.last().unwrap();
+                let child_id = self.trees[i].find_or_add_child(node_id, observed, player);
+                path.push(child_id);
+            }
+
+            let child_id = *paths[mover_tree].last().unwrap();
+            if expanded {
+                self.trees[mover_tree].nodes[child_id].availability += 1;
+            }
+
+            state = state.apply_action_with(action, &mut self.rng);
+            if expanded {
+                break;
+            }
+        }
+
+        let mut rng = Rng::with_seed(self.rng.u64(..));
+        let rewards = match Mcts::rollout(&state, &mut rng) {
+            Reward::Players(rewards) => rewards,
+            Reward::TwoPlayer(_) => unreachable!("current_player is implemented"),
+        };
+
+        for (tree, path) in self.trees.iter_mut().zip(&paths) {
+            tree.backpropagate(path, &rewards);
+        }
+    }
+
+    /// An untried action of the player to move, or the best legal one by
+    /// UCB1 over availability counts. Returns whether the action is new
+    fn select_action(&mut self, state: &T, tree: usize, path: &[usize]) -> (T::Action, bool) {
+        let tree = &mut self.trees[tree];
+        let node_id = *path.last().unwrap();
+        let mut actions = state.possible_actions(&mut self.rng);
+        let mut available = vec![];
+
+        for &child_id in &tree.nodes[node_id].child_ids {
+            let action = tree.nodes[child_id].action.as_ref().unwrap();
+            if let Some(i) = actions.iter().position(|other| other == action) {
+                actions.swap_remove(i);
+                available.push(child_id);
+            }
+        }
+
+        for &child_id in &available {
+            tree.nodes[child_id].availability += 1;
+        }
+
+        if let Some(action) = actions.pop() {
+            return (action, true);
+        }
+
+        let best = select_available(&available, self.c, |child_id| {
+            let child = &tree.nodes[child_id];
+            (child.num_sims, child.mean_score(), child.availability)
+        })
+        .expect("a state that isn't terminal has legal actions");
+
+        (tree.nodes[best].action.clone().unwrap(), false)
+    }
+
+    /// The root action chosen by the final move selection, along with the
+    /// criterion that chose it. `None` when the root state has no actions
+    pub fn final_action(&self) -> Option<(T::Action, SelectedBy)> {
+        let root_state = self.root_state.as_ref().unwrap();
+        self.tree()
+            .select_root_action(self.final_move, root_state, &self.rng)
+    }
+}
+
+impl<T> SearchDriver for Ismcts<T>
+where
+    T: Determinize + Clone,
+    T::Action: Display,
+{
+    type Action = T::Action;
+
+    fn iterate(&mut self) {
+        let root_state = self.root_state.as_ref().unwrap();
+        let state = root_state.determinize(self.root_player, &mut self.rng);
+        self.iterate_determinization(state);
+    }
+
+    fn size(&self) -> usize {
+        self.trees.iter().map(|tree| tree.nodes.len()).sum()
+    }
+
+    /// Estimate of the memory used by the nodes in bytes
+    fn memory_usage(&self) -> usize {
+        self.trees
+            .iter()
+            .flat_map(|tree| &tree.nodes)
+            .map(|node| {
+                std::mem::size_of::<IsmctsNode<T::Action>>()
+                    + node.child_ids.capacity() * std::mem::size_of::<usize>()
+            })
+            .sum()
+    }
+
+    fn search_info(&self, iterations: usize, elapsed: Duration) -> SearchInfo<T::Action> {
+        let selected = self
+            .final_action()
+            .filter(|_| !self.tree().nodes[0].child_ids.is_empty());
+
+        SearchInfo {
+            iterations,
+            elapsed,
+            nodes: self.size(),
+            memory: self.memory_usage(),
+            ..self.tree().root_info(selected)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fastrand::Rng;
+
+    use super::{Determinize, Ismcts, IsmctsVariant};
+    use crate::{
+        limits::SearchLimits,
+        state::{tests::quiet, State},
+    };
+
+    /// Both players hold one of three cards, the third one is put away.
+    /// The first player either passes, scoring 0.6, or shows their card,
+    /// winning when it's higher than the opponent's
+    #[derive(Clone)]
+    struct HighCard {
+        cards: [usize; 2],
+        result: Option<f32>,
+    }
+
+    const PASS: usize = 0;
+    const SHOW: usize = 1;
+
+    impl State for HighCard {
+        type Action = usize;
+
+        fn possible_actions(&self, rng: &mut Rng) -> Vec<Self::Action> {
+            let mut actions = match self.result {
+                Some(_) => vec![],
+                None => vec![PASS, SHOW],
+            };
+            rng.shuffle(&mut actions);
+
+            actions
+        }
+
+        fn apply_action(&self, action: Self::Action) -> Self {
+            let result = match action {
+                PASS => 0.6,
+                _ => (self.cards[0] > self.cards[1]) as usize as f32,
+            };
+
+            HighCard {
+                cards: self.cards,
+                result: Some(result),
+            }
+        }
+
+        fn last_action(&self) -> Option<Self::Action> {
+            None
+        }
+
+        fn reward(&self, _perspective: &Self) -> f32 {
+            unreachable!()
+        }
+
+        fn current_player(&self) -> Option<usize> {
+            Some(0)
+        }
+
+        fn rewards(&self) -> Vec<f32> {
+            let result = self.result.unwrap_or(0.5);
+            vec![result, 1. - result]
+        }
+
+        fn is_terminal(&mut self, _depth: usize) -> bool {
+            self.result.is_some()
+        }
+    }
+
+    impl Determinize for HighCard {
+        fn determinize(&self, observer: usize, rng: &mut Rng) -> Self {
+            let mut cards = self.cards;
+            let unseen = (0..3)
+                .filter(|&card| card != cards[observer])
+                .collect::<Vec<_>>();
+            cards[1 - observer] = unseen[rng.usize(..unseen.len())];
+
+            HighCard {
+                cards,
+                result: self.result,
+            }
+        }
+    }
+
+    #[test]
+    fn test_ismcts_ignores_hidden_cards() {
+        // the opponent's lowest card would make showing a sure win,
+        // but it might as well be the highest card
+        let game = HighCard {
+            cards: [1, 0],
+            result: None,
+        };
+
+        for variant in [
+            IsmctsVariant::SingleObserver,
+            IsmctsVariant::MultipleObserver,
+        ] {
+            let mut searcher = quiet(Ismcts::new(variant));
+            searcher.set_seed(2);
+
+            assert_eq!(
+                searcher.search(&SearchLimits::iterations(2000), game.clone()),
+                PASS
+            );
+
+            let tree = searcher.tree();
+            let show = &tree.nodes[tree.find_child(0, &SHOW).unwrap()];
+            assert!((show.mean_score() - 0.5).abs() < 0.1);
+        }
+    }
+
+    /// The first player is dealt a card, 0 or 1, and plays it face up or
+    /// face down. The second player wins by guessing it
+    #[derive(Clone)]
+    struct FaceDown {
+        card: Option<usize>,
+        played: Option<usize>,
+        guess: Option<usize>,
+    }
+
+    const DOWN: [usize; 2] = [0, 1];
+    const UP: [usize; 2] = [2, 3];
+    const GUESS: [usize; 2] = [4, 5];
+    /// How the second player sees either card played face down
+    const FACE_DOWN: usize = 6;
+
+    impl State for FaceDown {
+        type Action = usize;
+
+        fn possible_actions(&self, rng: &mut Rng) -> Vec<Self::Action> {
+            let mut actions = match (self.card, self.played, self.guess) {
+                (Some(card), None, _) => vec![DOWN[card], UP[card]],
+                (Some(_), Some(_), None) => GUESS.to_vec(),
+                _ => vec![],
+            };
+            rng.shuffle(&mut actions);
+
+            actions
+        }
+
+        fn apply_action(&self, action: Self::Action) -> Self {
+            let mut game = self.clone();
+            match (self.card, self.played) {
+                (None, _) => game.card = Some(action),
+                (Some(_), None) => game.played = Some(action),
+                (Some(_), Some(_)) => game.guess = Some(action - GUESS[0]),
+            }
+
+            game
+        }
+
+        fn last_action(&self) -> Option<Self::Action> {
+            None
+        }
+
+        fn reward(&self, _perspective: &Self) -> f32 {
+            unreachable!()
+        }
+
+        fn current_player(&self) -> Option<usize> {
+            Some(self.played.is_some() as usize)
+        }
+
+        fn rewards(&self) -> Vec<f32> {
+            let guessed = (self.guess == self.card) as usize as f32;
+            vec![1. - guessed, guessed]
+        }
+
+        fn is_terminal(&mut self, _depth: usize) -> bool {
+            self.guess.is_some()
+        }
+
+        fn chance_outcomes(&self) -> Option<Vec<(Self::Action, f32)>> {
+            self.card.is_none().then(|| vec![(0, 0.5), (1, 0.5)])
+        }
+    }
+
+    impl Determinize for FaceDown {
+        /// Only searched before the deal, when nothing is hidden yet
+        fn determinize(&self, _observer: usize, _rng: &mut Rng) -> Self {
+            self.clone()
+        }
+
+        fn observed_action(&self, action: &Self::Action, observer: usize) -> Self::Action {
+            match observer {
+                1 if DOWN.contains(action) => FACE_DOWN,
+                _ => *action,
+            }
+        }
+    }
+
+    #[test]
+    fn test_multiple_observer_hides_actions() {
+        let mut searcher = quiet(Ismcts::new(IsmctsVariant::MultipleObserver));
+        searcher.set_seed(5);
+
+        let game = FaceDown {
+            card: None,
+            played: None,
+            guess: None,
+        };
+        searcher.search(&SearchLimits::iterations(4000), game);
+
+        // the second player sees either card played face down the same way
+        let tree = searcher.tree();
+        let opponent = &searcher.trees[1];
+        let down_ids = DOWN.map(|down| tree.find_child(0, &down).unwrap());
+        let face_down = opponent.find_child(0, &FACE_DOWN).unwrap();
+        assert!(DOWN
+            .iter()
+            .all(|down| opponent.find_child(0, down).is_none()));
+        assert_eq!(
+            opponent.nodes[face_down].num_sims(),
+            down_ids.iter().map(|&id| tree.nodes[id].num_sims()).sum()
+        );
+
+        // guessing from the second player's tree can't follow the card,
+        // whereas the first player's tree would reveal it
+        for down_id in down_ids {
+            assert!(tree.nodes[down_id].mean_score() > 0.35);
+        }
+        for up in UP {
+            let up_id = tree.find_child(0, &up).unwrap();
+            assert!(tree.nodes[up_id].mean_score() < tree.nodes[down_ids[up - UP[0]]].mean_score());
+        }
+    }
+}
@@ -0,0 +1,26 @@
+pub mod atomic;
+pub mod bound;
+pub mod compact;
+pub mod driver;
+pub mod final_move;
+pub mod gumbel;
+pub mod hybrid;
+pub mod ismcts;
+pub mod limits;
+pub mod mcts;
+pub mod minimax;
+pub mod nested;
+pub mod node;
+pub mod observer;
+pub mod open_loop;
+pub mod policy;
+pub mod progressive;
+pub mod rave;
+pub mod rollout;
+pub mod single_player;
+pub mod state;
+pub mod tree;
+
+pub use limits::SearchLimits;
+pub use mcts::Mcts;
+pub use state::State;
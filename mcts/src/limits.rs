@@ -0,0 +1,70 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{state::State, tree::Tree};
+
+/// When to stop searching, the search ends as soon as any of the set
+/// limits is reached. Without any limits, a search only stops when
+/// the stop flag is raised or the root is proven
+#[derive(Clone, Default)]
+pub struct SearchLimits {
+    pub time: Option<Duration>,
+    pub iterations: Option<usize>,
+    /// Maximum number of nodes in the tree
+    pub nodes: Option<usize>,
+    /// Maximum estimated memory usage of the tree, in bytes
+    pub memory: Option<usize>,
+    /// Raised by another thread to stop the search
+    pub stop: Option<Arc<AtomicBool>>,
+}
+
+impl SearchLimits {
+    pub fn time(time: Duration) -> Self {
+        SearchLimits {
+            time: Some(time),
+            ..Default::default()
+        }
+    }
+
+    pub fn millis(millis: u64) -> Self {
+        SearchLimits::time(Duration::from_millis(millis))
+    }
+
+    pub fn iterations(iterations: usize) -> Self {
+        SearchLimits {
+            iterations: Some(iterations),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_reached<T>(&self, started: Instant, iterations: usize, tree: &Tree<T>) -> bool
+    where
+        T: State,
+    {
+        self.is_reached_with(started, iterations, tree.size(), tree.memory_usage())
+    }
+
+    /// [`SearchLimits::is_reached`] for trees other than [`Tree`], given
+    /// their number of nodes and memory usage
+    pub fn is_reached_with(
+        &self,
+        started: Instant,
+        iterations: usize,
+        size: usize,
+        memory: usize,
+    ) -> bool {
+        self.iterations.is_some_and(|max| iterations >= max)
+            || self.nodes.is_some_and(|max| size >= max)
+            || self.memory.is_some_and(|max| memory >= max)
+            || self
+                .stop
+                .as_ref()
+                .is_some_and(|stop| stop.load(Ordering::Relaxed))
+            || self.time.is_some_and(|max| started.elapsed() >= max)
+    }
+}
@@ -0,0 +1,1168 @@
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        RwLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use fastrand::Rng;
+
+use crate::{
+    bound::TreeBound,
+    driver::Reporter,
+    final_move::{FinalMoveSelection, SelectedBy},
+    gumbel::{gumbel, logit, GumbelRoot},
+    hybrid::{rollout_action, HybridMinimax},
+    limits::SearchLimits,
+    minimax::ImplicitMinimax,
+    node::Proof,
+    observer::{ChildInfo, SearchInfo, SearchObserver},
+    policy::SelectionPolicy,
+    progressive::{ProgressiveBias, ProgressiveWidening},
+    rave::RaveSchedule,
+    rollout::{RolloutPolicy, UniformRollout},
+    state::{sample_weighted, Reward, State},
+    tree::Tree,
+};
+
+pub struct Mcts<T>
+where
+    T: State + Clone,
+{
+    tree: Tree<T>,
+    root_id: Option<usize>,
+    reporter: Reporter<T::Action>,
+    /// Replaces UCT at the root of single threaded searches
+    gumbel: Option<GumbelRoot>,
+    rollout_policy: Box<dyn RolloutPolicy<T>>,
+}
+
+impl<T> Default for Mcts<T>
+where
+    T: State + Clone,
+{
+    fn default() -> Self {
+        Mcts {
+            tree: Tree::default(),
+            root_id: None,
+            reporter: Reporter::default(),
+            gumbel: None,
+            rollout_policy: Box::new(UniformRollout),
+        }
+    }
+}
+
+impl<T> Mcts<T>
+where
+    T: State + Clone,
+{
+    pub fn with_policy(policy: Box<dyn SelectionPolicy<T>>) -> Self {
+        let mut mcts = Mcts::default();
+        mcts.tree.set_policy(policy);
+
+        mcts
+    }
+
+    /// Share nodes between transpositions, see [`Tree::set_transpositions`]
+    pub fn set_transpositions(&mut self, enabled: bool) {
+        self.tree.set_transpositions(enabled);
+    }
+
+    /// See [`Tree::set_rave`]
+    pub fn set_rave(&mut self, schedule: Option<RaveSchedule>) {
+        self.tree.set_rave(schedule);
+    }
+
+    /// See [`Tree::set_progressive_widening`]
+    pub fn set_progressive_widening(&mut self, widening: Option<ProgressiveWidening>) {
+        self.tree.set_progressive_widening(widening);
+    }
+
+    /// See [`Tree::set_progressive_bias`]
+    pub fn set_progressive_bias(&mut self, bias: Option<ProgressiveBias>) {
+        self.tree.set_progressive_bias(bias);
+    }
+
+    /// See [`ImplicitMinimax`]
+    pub fn set_implicit_minimax(&mut self, minimax: Option<ImplicitMinimax<T>>) {
+        self.tree.set_implicit_minimax(minimax);
+    }
+
+    /// How simulations choose their actions, uniformly random by default.
+    /// Parallel searches give every thread or tree an untrained fork
+    pub fn set_rollout_policy(&mut self, policy: Box<dyn RolloutPolicy<T>>) {
+        self.rollout_policy = policy;
+    }
+
+    /// See [`HybridMinimax`]
+    pub fn set_hybrid_minimax(&mut self, hybrid: HybridMinimax) {
+        self.tree.set_hybrid_minimax(hybrid);
+    }
+
+    /// See [`TreeBound`]
+    pub fn set_tree_bound(&mut self, bound: Option<TreeBound>) {
+        self.tree.set_bound(bound);
+    }
+
+    /// Receives the search reports instead of the console,
+    /// see [`ConsoleObserver`](crate::observer::ConsoleObserver)
+    pub fn set_observer(&mut self, observer: Box<dyn SearchObserver<T::Action>>) {
+        self.reporter.set_observer(observer);
+    }
+
+    /// How often the observer gets a report while searching, by default
+    /// it's only notified when the search finishes
+    pub fn set_report_interval(&mut self, interval: Option<Duration>) {
+        self.reporter.set_interval(interval);
+    }
+
+    /// Searches with the same seed and an iteration limit grow identical trees
+    /// and pick the same action, except for tree parallel searches whose
+    /// threads interleave differently from run to run. See [`Tree::set_seed`]
+    pub fn set_seed(&mut self, seed: u64) {
+        self.tree.set_seed(seed);
+    }
+
+    /// See [`FinalMoveSelection`]
+    pub fn set_final_move_selection(&mut self, selection: FinalMoveSelection) {
+        self.tree.set_final_move_selection(selection);
+    }
+
+    /// Search the root with sequential halving instead of UCT, see
+    /// [`GumbelRoot`]. Only used by [`Mcts::search`], which splits the
+    /// iteration limit over the rounds, or else the time limit
+    pub fn set_gumbel_root(&mut self, gumbel: Option<GumbelRoot>) {
+        self.gumbel = gumbel;
+    }
+
+    /// Policy improvement target over all root actions, for training a
+    /// prior, see [`GumbelRoot::improved_policy`]. Works after any search
+    pub fn policy_target(&self) -> Vec<(T::Action, f32)> {
+        let Some(root_id) = self.root_id else {
+            return vec![];
+        };
+        let root = &self.tree.nodes[root_id];
+
+        let mut actions = root.child_actions.clone();
+        let mut priors = vec![];
+        let mut num_sims = vec![];
+        let mut scores = vec![];
+        for &child_id in &root.child_ids {
+            let child = &self.tree.nodes[child_id];
+            priors.push(child.prior);
+            num_sims.push(child.num_sims());
+            scores.push(if child.is_explored() {
+                child.mean_score()
+            } else {
+                0.
+            });
+        }
+
+        actions.extend(root.actions.iter().cloned());
+        priors.extend(&root.action_priors);
+        num_sims.resize(actions.len(), 0);
+        scores.resize(actions.len(), 0.);
+
+        let policy = self
+            .gumbel
+            .unwrap_or_default()
+            .improved_policy(&priors, &num_sims, &scores);
+
+        actions.into_iter().zip(policy).collect()
+    }
+
+    /// See [`Tree::set_solver`]
+    pub fn set_solver(&mut self, enabled: bool) {
+        self.tree.set_solver(enabled);
+    }
+
+    /// The proven result of the root position from the perspective of
+    /// the player to move, with the number of plies until the game ends
+    pub fn proven_result(&self) -> Option<Proof> {
+        let root_id = self.root_id?;
+        self.tree.nodes[root_id].proof().map(Proof::invert)
+    }
+
+    pub fn tree(&self) -> &Tree<T> {
+        &self.tree
+    }
+
+    pub fn root_id(&self) -> Option<usize> {
+        self.root_id
+    }
+
+    /// Move the root down the tree along the actions that were played,
+    /// keeping the statistics of the new root's subtree and discarding
+    /// everything else. When one of the actions was never expanded, the
+    /// whole tree is dropped and the next search starts from scratch.
+    /// The next search is expected to be of the new root's state, see
+    /// [`Mcts::search`].
+    ///
+    /// Returns whether the subtree could be reused
+    pub fn advance_root(&mut self, actions: &[T::Action]) -> bool {
+        let Some(mut node_id) = self.root_id else {
+            return false;
+        };
+
+        for action in actions {
+            match self.tree.find_child(node_id, action) {
+                Some(child_id) => node_id = child_id,
+                None => {
+                    self.reset();
+                    return false;
+                }
+            }
+        }
+
+        self.root_id = Some(self.tree.retain_subtree(node_id));
+        true
+    }
+
+    /// Throw away the tree, the next search will start from
+    /// the given root state
+    pub fn reset(&mut self) {
+        self.tree.clear();
+        self.root_id = None;
+    }
+
+    /// Play random actions until reaching a terminal state, returns the
+    /// reward from the perspective of the player who moved into `from`,
+    /// or of every player
+    pub(crate) fn rollout(from: &T, rng: &mut Rng) -> Reward {
+        Self::rollout_with(from, &mut vec![], None, &mut UniformRollout, rng)
+    }
+
+    /// [`Mcts::rollout`] choosing actions with `policy`, which learns from
+    /// the result. `history` holds the actions leading from the root to
+    /// `from`, the simulation pushes its actions onto it. With a minimax
+    /// depth, wins and losses this many plies ahead are looked for before
+    /// every move instead, see [`HybridMinimax::rollout_depth`]
+    pub(crate) fn rollout_with(
+        from: &T,
+        history: &mut Vec<T::Action>,
+        minimax_depth: Option<usize>,
+        policy: &mut dyn RolloutPolicy<T>,
+        rng: &mut Rng,
+    ) -> Reward {
+        let mut state = from.clone();
+        let start = history.len();
+        // who played every action: None for chance outcomes, otherwise the
+        // player, or for two players whether it's the one who moved into `from`
+        let mut movers = vec![];
+        let mut own_move = false;
+
+        while !state.is_terminal(history.len() - start) {
+            let action = match state.chance_outcomes() {
+                Some(mut outcomes) => {
+                    let i = sample_weighted(outcomes.iter().map(|(_, p)| *p), rng);
+                    movers.push(None);
+                    outcomes.swap_remove(i).0
+                }
+                None => {
+                    movers.push(Some((state.current_player(), own_move)));
+                    own_move = !own_move;
+
+                    match minimax_depth {
+                        Some(depth) => rollout_action(&state, depth, rng),
+                        None => policy.select(&state, history, rng),
+                    }
+                }
+            };
+            history.push(action.clone());
+
+            state = state.apply_action_with(action, rng);
+        }
+
+        let reward = match state.current_player() {
+            Some(_) => Reward::Players(state.rewards()),
+            None => Reward::TwoPlayer(state.reward(from)),
+        };
+
+        let rewards = movers
+            .into_iter()
+            .map(|mover| {
+                mover.map(|(player, own_move)| match (&reward, player) {
+                    (Reward::Players(rewards), Some(player)) => rewards[player],
+                    (Reward::TwoPlayer(reward), _) if own_move => *reward,
+                    (Reward::TwoPlayer(reward), _) => 1. - reward,
+                    (Reward::Players(_), None) => unreachable!(),
+                })
+            })
+            .collect::<Vec<_>>();
+        policy.update(history, start, &rewards);
+
+        reward
+    }
+}
+
+impl<T> Mcts<T>
+where
+    T: State + Clone,
+    T::Action: Display,
+{
+    /// Search `root_state`, continuing from the current tree unless its root
+    /// has a different hash key, see [`Mcts::advance_root`]
+    pub fn search(&mut self, limits: &SearchLimits, root_state: T) -> T::Action {
+        let started = Instant::now();
+        let root_id = self.prepare_root(root_state);
+        self.reporter.start(started);
+        let policy = self.rollout_policy.as_mut();
+        policy.reset();
+
+        if let Some(gumbel) = self.gumbel {
+            let (iterations, action) = Self::run_gumbel(
+                &mut self.tree,
+                root_id,
+                started,
+                limits,
+                gumbel,
+                policy,
+                Some(&mut self.reporter),
+            );
+
+            let mut info = self
+                .tree
+                .search_info(root_id, iterations, started.elapsed());
+            let action = match action {
+                Some(action) => {
+                    info.value = info
+                        .children
+                        .iter()
+                        .find(|child| child.action == action && child.num_sims > 0)
+                        .map(|child| child.mean_score);
+                    info.best_action = Some(action.clone());
+                    info.selected_by = Some(SelectedBy::SequentialHalving);
+                    action
+                }
+                None => self.tree.best_action(root_id),
+            };
+            self.reporter.finish(info);
+
+            return action;
+        }
+
+        let report = Some((&mut self.reporter, 0));
+        let mut iterations = Self::run(&mut self.tree, root_id, started, limits, policy, report);
+        iterations += self.extend(root_id, started, iterations, limits);
+
+        let info = self
+            .tree
+            .search_info(root_id, iterations, started.elapsed());
+        self.reporter.finish(info);
+
+        self.tree.best_action(root_id)
+    }
+
+    /// Single threaded search loop, returns the number of iterations.
+    /// `report` gets periodic snapshots of the search, counting the given
+    /// number of iterations that were run before
+    fn run(
+        tree: &mut Tree<T>,
+        root_id: usize,
+        started: Instant,
+        limits: &SearchLimits,
+        policy: &mut dyn RolloutPolicy<T>,
+        mut report: Option<(&mut Reporter<T::Action>, usize)>,
+    ) -> usize {
+        let mut iterations = 0;
+        let mut rng = tree.fork_rng();
+
+        loop {
+            if limits.is_reached(started, iterations, tree) {
+                break;
+            }
+
+            if tree.nodes[root_id].proof().is_some() {
+                break;
+            }
+
+            if let Some((reporter, before)) = report.as_mut() {
+                let iterations = *before + iterations;
+                reporter.report(|| tree.search_info(root_id, iterations, started.elapsed()));
+            }
+
+            tree.make_room(root_id);
+            Self::iterate(tree, root_id, root_id, policy, &mut rng);
+
+            iterations += 1;
+        }
+
+        iterations
+    }
+
+    /// Select, expand, simulate and backpropagate once, starting the
+    /// selection at `node_id`, a child of the root or the root itself
+    fn iterate(
+        tree: &mut Tree<T>,
+        root_id: usize,
+        node_id: usize,
+        policy: &mut dyn RolloutPolicy<T>,
+        rng: &mut Rng,
+    ) {
+        let mut path = match node_id == root_id {
+            true => vec![],
+            false => vec![root_id],
+        };
+        path.extend(tree.select_path(node_id));
+
+        let leaf_id = *path.last().unwrap();
+        let child_id = tree.expand(leaf_id);
+        if child_id != leaf_id {
+            path.push(child_id);
+        }
+
+        let mut history = tree.path_actions(&path);
+        let start = history.len();
+        let depth = tree.hybrid_minimax().rollout_depth;
+        let reward = tree.leaf_reward(child_id, |state| {
+            Self::rollout_with(state, &mut history, depth, policy, rng)
+        });
+        tree.backpropagate(&path, &history[start..], &reward);
+    }
+
+    /// Sequential halving over the root actions, see [`GumbelRoot`]. Returns
+    /// the number of iterations and the last action left, if any. The rounds
+    /// get an equal share of the iteration limit, or else of the time limit.
+    /// Without either, the first round lasts until the search stops. The
+    /// tree isn't pruned meanwhile, with a [`TreeBound`] it stops expanding
+    fn run_gumbel(
+        tree: &mut Tree<T>,
+        root_id: usize,
+        started: Instant,
+        limits: &SearchLimits,
+        gumbel_root: GumbelRoot,
+        policy: &mut dyn RolloutPolicy<T>,
+        mut reporter: Option<&mut Reporter<T::Action>>,
+    ) -> (usize, Option<T::Action>) {
+        let mut rng = tree.fork_rng();
+
+        while !tree.nodes[root_id].actions.is_empty() && tree.expand(root_id) != root_id {}
+
+        // (index in child_ids, child id, gumbel noise plus logit)
+        let mut candidates = tree.nodes[root_id]
+            .child_ids
+            .iter()
+            .enumerate()
+            .filter(|(_, &child_id)| !matches!(tree.nodes[child_id].proof(), Some(Proof::Loss(_))))
+            .map(|(i, &child_id)| {
+                let noise = gumbel(&mut rng) + logit(tree.nodes[child_id].prior);
+                (i, child_id, noise)
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| b.2.total_cmp(&a.2));
+        candidates.truncate(gumbel_root.num_sampled.max(1));
+
+        let rounds = candidates.len().next_power_of_two().trailing_zeros().max(1) as usize;
+        let mut iterations = 0;
+        let mut round = 0;
+
+        'search: while candidates.len() > 1 {
+            round += 1;
+            let sims_each = limits
+                .iterations
+                .map(|budget| (budget / (rounds * candidates.len())).max(1));
+            let deadline = limits
+                .time
+                .map(|time| started + time * round as u32 / rounds as u32);
+            let round_over = |sims: usize| match (sims_each, deadline) {
+                (Some(sims_each), _) => sims >= sims_each,
+                (None, Some(deadline)) => Instant::now() >= deadline,
+                (None, None) => false,
+            };
+
+            // the candidates take turns, so they get an equal share
+            // of the round however it ends
+            let mut sims = 0;
+            while !round_over(sims) {
+                for &(_, child_id, _) in &candidates {
+                    if limits.is_reached(started, iterations, tree)
+                        || tree.nodes[root_id].proof().is_some()
+                    {
+                        break 'search;
+                    }
+
+                    if let Some(reporter) = reporter.as_deref_mut() {
+                        reporter
+                            .report(|| tree.search_info(root_id, iterations, started.elapsed()));
+                    }
+
+                    Self::iterate(tree, root_id, child_id, policy, &mut rng);
+                    iterations += 1;
+                }
+                sims += 1;
+            }
+
+            Self::rank_candidates(tree, &mut candidates, gumbel_root);
+            candidates.truncate(candidates.len().div_ceil(2));
+        }
+
+        Self::rank_candidates(tree, &mut candidates, gumbel_root);
+        let action = candidates
+            .first()
+            .map(|&(i, _, _)| tree.child_action(root_id, i));
+
+        (iterations, action)
+    }
+
+    /// Sort the candidates by their noisy logit plus transformed mean score
+    fn rank_candidates(
+        tree: &Tree<T>,
+        candidates: &mut [(usize, usize, f32)],
+        gumbel_root: GumbelRoot,
+    ) {
+        let max_sims = candidates
+            .iter()
+            .map(|&(_, child_id, _)| tree.nodes[child_id].num_sims())
+            .max()
+            .unwrap_or(0);
+        let value = |&(_, child_id, noise): &(usize, usize, f32)| {
+            let child = &tree.nodes[child_id];
+            match child.is_explored() {
+                true => noise + gumbel_root.transform(child.mean_score(), max_sims),
+                false => noise,
+            }
+        };
+
+        candidates.sort_by(|a, b| value(b).total_cmp(&value(a)));
+    }
+
+    /// The root to search `root_state` from. The current tree is dropped
+    /// when its root has a different [`State::hash_key`]. States without
+    /// one can't be told apart, their tree is kept until it's advanced
+    /// with [`Mcts::advance_root`] or [`Mcts::reset`]
+    fn prepare_root(&mut self, root_state: T) -> usize {
+        if let Some(root_id) = self.root_id {
+            let root_hash = self.tree.get_state_ref(root_id).hash_key();
+            if let (Some(root_hash), Some(hash)) = (root_hash, root_state.hash_key()) {
+                if root_hash != hash {
+                    self.reset();
+                }
+            }
+        }
+
+        let root_id = match self.root_id {
+            Some(root_id) => root_id,
+            None => self.tree.add_state(root_state, None),
+        };
+        self.root_id = Some(root_id);
+
+        root_id
+    }
+
+    /// With [`FinalMoveSelection::MaxRobustChild`], keep searching while the
+    /// max and robust child differ. The time limit still counts from the
+    /// start of the search. Returns the number of extra iterations
+    fn extend(
+        &mut self,
+        root_id: usize,
+        started: Instant,
+        iterations: usize,
+        limits: &SearchLimits,
+    ) -> usize {
+        let FinalMoveSelection::MaxRobustChild { extension } = self.tree.final_move_selection()
+        else {
+            return 0;
+        };
+
+        let max_iterations = (iterations as f32 * extension) as usize;
+        // check for agreement every percent of the original search
+        let chunk = (iterations / 100).max(1);
+        let mut extra = 0;
+
+        while extra < max_iterations
+            && self.tree.final_action(root_id).1 != SelectedBy::MaxRobustChild
+        {
+            let limits = SearchLimits {
+                iterations: Some(chunk.min(max_iterations - extra)),
+                ..limits.clone()
+            };
+            let policy = self.rollout_policy.as_mut();
+            let report = Some((&mut self.reporter, iterations + extra));
+            let done = Self::run(&mut self.tree, root_id, started, &limits, policy, report);
+            if done == 0 {
+                break;
+            }
+
+            extra += done;
+        }
+
+        extra
+    }
+
+    pub fn simulate(&mut self, node_id: usize) -> Reward {
+        let mut rng = self.tree.fork_rng();
+        let depth = self.tree.hybrid_minimax().rollout_depth;
+        let policy = self.rollout_policy.as_mut();
+        Self::rollout_with(
+            self.tree.get_state_ref(node_id),
+            &mut vec![],
+            depth,
+            policy,
+            &mut rng,
+        )
+    }
+
+    /// See [`Tree::backpropagate`]
+    pub fn backpropagate(&mut self, path: &[usize], playout: &[T::Action], reward: &Reward) {
+        self.tree.backpropagate(path, playout, reward);
+    }
+}
+
+impl<T> Mcts<T>
+where
+    T: State + Clone + Send + Sync,
+    T::Action: Display + Send + Sync,
+{
+    /// Tree parallel search: `threads` workers share a single tree, only
+    /// taking a write lock to expand it. Virtual losses steer concurrent
+    /// workers onto different paths
+    pub fn search_parallel(
+        &mut self,
+        limits: &SearchLimits,
+        root_state: T,
+        threads: usize,
+    ) -> T::Action {
+        let started = Instant::now();
+        let root_id = self.prepare_root(root_state);
+        let seeds = (0..threads)
+            .map(|_| self.tree.fork_rng().u64(..))
+            .collect::<Vec<_>>();
+        let tree = RwLock::new(std::mem::take(&mut self.tree));
+        let total_iterations = AtomicUsize::new(0);
+
+        let thread_iterations = thread::scope(|s| {
+            let workers = seeds
+                .into_iter()
+                .map(|seed| {
+                    let (tree, total_iterations) = (&tree, &total_iterations);
+                    let policy = self.rollout_policy.fork();
+                    s.spawn(move || {
+                        Self::parallel_worker(
+                            tree,
+                            root_id,
+                            started,
+                            limits,
+                            total_iterations,
+                            policy,
+                            seed,
+                        )
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            if let Some(interval) = self.reporter.interval() {
+                self.reporter.start(started);
+
+                while !workers.iter().all(|worker| worker.is_finished()) {
+                    thread::sleep(interval.min(Duration::from_millis(10)));
+                    self.reporter.report(|| {
+                        let iterations = total_iterations.load(Ordering::Relaxed);
+                        let tree = tree.read().unwrap();
+                        tree.search_info(root_id, iterations, started.elapsed())
+                    });
+                }
+            }
+
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        self.tree = tree.into_inner().unwrap();
+
+        let iterations = thread_iterations.iter().sum();
+        let mut info = self
+            .tree
+            .search_info(root_id, iterations, started.elapsed());
+        info.thread_iterations = thread_iterations;
+        self.reporter.finish(info);
+
+        self.tree.best_action(root_id)
+    }
+
+    /// Root parallel search: `trees` independent trees, each with its own seed,
+    /// are searched on separate threads. The first tree continues from the
+    /// current tree, which it replaces afterwards. The statistics of the root
+    /// children are merged over all trees before choosing an action.
+    /// The limits apply to every tree separately. Periodic reports
+    /// show the first tree only
+    pub fn search_root_parallel(
+        &mut self,
+        limits: &SearchLimits,
+        root_state: T,
+        trees: usize,
+    ) -> T::Action {
+        let started = Instant::now();
+        let root_id = self.prepare_root(root_state.clone());
+        let mut rng = self.tree.fork_rng();
+
+        let mut forest = vec![(std::mem::take(&mut self.tree), root_id)];
+        for _ in 1..trees {
+            let mut tree = forest[0].0.new_like(rng.u64(..));
+            let root_id = tree.add_state(root_state.clone(), None);
+            forest.push((tree, root_id));
+        }
+
+        self.reporter.start(started);
+        let mut report = Some((&mut self.reporter, 0));
+        let tree_iterations = thread::scope(|s| {
+            let workers = forest
+                .iter_mut()
+                .map(|(tree, root_id)| {
+                    let mut policy = self.rollout_policy.fork();
+                    let report = report.take();
+                    s.spawn(move || {
+                        Self::run(tree, *root_id, started, limits, policy.as_mut(), report)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        let elapsed = started.elapsed();
+
+        // (action, simulations, score, squared score) summed over all trees
+        let mut merged: Vec<(T::Action, usize, f32, f32)> = vec![];
+        for (tree, root_id) in &forest {
+            let root = &tree.nodes[*root_id];
+            for (i, &child_id) in root.child_ids.iter().enumerate() {
+                let action = tree.child_action(*root_id, i);
+                let node = &tree.nodes[child_id];
+                let n = node.num_sims() as f32;
+                let score_sq = if node.is_explored() {
+                    n * (node.variance() + node.mean_score().powi(2))
+                } else {
+                    0.
+                };
+
+                match merged.iter_mut().find(|(other, ..)| *other == action) {
+                    Some((_, num_sims, score, sum_sq)) => {
+                        *num_sims += node.num_sims();
+                        *score += node.score();
+                        *sum_sq += score_sq;
+                    }
+                    None => merged.push((action, node.num_sims(), node.score(), score_sq)),
+                }
+            }
+        }
+
+        // a proof from any tree beats the merged statistics
+        let proven = forest.iter().find_map(|(tree, root_id)| {
+            matches!(tree.nodes[*root_id].proof(), Some(Proof::Loss(_)))
+                .then(|| tree.best_action(*root_id))
+        });
+        let selected = match proven {
+            Some(action) => Some((action, SelectedBy::Proof)),
+            None => {
+                let stats = merged
+                    .iter()
+                    .map(|(_, num_sims, score, _)| (*num_sims, score / *num_sims as f32))
+                    .collect::<Vec<_>>();
+                let selection = forest[0].0.final_move_selection();
+                selection
+                    .select(&stats)
+                    .map(|(i, selected_by)| (merged[i].0.clone(), selected_by))
+            }
+        };
+        // no tree ran an iteration, fall back like a single tree would
+        let (best_action, selected_by) =
+            selected.unwrap_or_else(|| forest[0].0.final_action(root_id));
+
+        // continue the principal variation in the tree
+        // that searched the best action the most
+        let mut pv = vec![best_action.clone()];
+        let best_child = forest
+            .iter()
+            .filter_map(|(tree, root_id)| Some((tree, tree.find_child(*root_id, &best_action)?)))
+            .max_by_key(|(tree, child_id)| tree.nodes[*child_id].num_sims());
+        if let Some((tree, child_id)) = best_child {
+            pv.extend(tree.continuation(child_id));
+        }
+
+        let children = merged
+            .into_iter()
+            .map(|(action, num_sims, score, sum_sq)| {
+                let mean_score = score / num_sims as f32;
+                ChildInfo {
+                    action,
+                    num_sims,
+                    mean_score,
+                    std_dev: Some(
+                        (sum_sq / num_sims as f32 - mean_score.powi(2))
+                            .max(0.)
+                            .sqrt(),
+                    ),
+                    proof: None,
+                }
+            })
+            .collect::<Vec<_>>();
+        let value = children
+            .iter()
+            .find(|child| child.action == best_action)
+            .map(|child| child.mean_score);
+
+        let info = SearchInfo {
+            iterations: tree_iterations.iter().sum(),
+            elapsed,
+            nodes: forest.iter().map(|(tree, _)| tree.size()).sum(),
+            memory: forest.iter().map(|(tree, _)| tree.memory_usage()).sum(),
+            depth: pv.len(),
+            best_action: Some(best_action.clone()),
+            value,
+            proof: forest
+                .iter()
+                .find_map(|(tree, root_id)| tree.nodes[*root_id].proof())
+                .map(Proof::invert),
+            pv,
+            children,
+            selected_by: Some(selected_by),
+            thread_iterations: tree_iterations,
+            finished: false,
+        };
+
+        self.tree = forest.swap_remove(0).0;
+        self.reporter.finish(info);
+
+        best_action
+    }
+
+    fn parallel_worker(
+        tree: &RwLock<Tree<T>>,
+        root_id: usize,
+        started: Instant,
+        limits: &SearchLimits,
+        total_iterations: &AtomicUsize,
+        mut policy: Box<dyn RolloutPolicy<T>>,
+        seed: u64,
+    ) -> usize {
+        let mut rng = Rng::with_seed(seed);
+        let mut iterations = 0;
+        let rollout_depth = tree.read().unwrap().hybrid_minimax().rollout_depth;
+
+        loop {
+            {
+                let tree = tree.read().unwrap();
+                // claim an iteration, so all threads together
+                // never run more than the iteration limit
+                let claimed = total_iterations.fetch_add(1, Ordering::Relaxed);
+                if limits.is_reached(started, claimed, &tree) {
+                    break;
+                }
+
+                if tree.nodes[root_id].proof().is_some() {
+                    break;
+                }
+            }
+
+            let mut path = tree
+                .read()
+                .unwrap()
+                .select_path_with(root_id, &mut rng, true);
+
+            let (state, proof, mut history) = {
+                let mut tree = tree.write().unwrap();
+                let node_id = *path.last().unwrap();
+                let child_id = tree.expand(node_id);
+                if child_id != node_id {
+                    tree.nodes[child_id].add_virtual_loss();
+                    path.push(child_id);
+                }
+
+                let history = tree.path_actions(&path);
+                (
+                    tree.get_state(child_id),
+                    tree.nodes[child_id].proof(),
+                    history,
+                )
+            };
+
+            let start = history.len();
+            let reward = match proof {
+                Some(proof) => Reward::TwoPlayer(proof.reward()),
+                None => Self::rollout_with(
+                    &state,
+                    &mut history,
+                    rollout_depth,
+                    policy.as_mut(),
+                    &mut rng,
+                ),
+            };
+
+            let tree = tree.read().unwrap();
+            tree.backpropagate(&path, &history[start..], &reward);
+            tree.revert_virtual_loss(&path);
+
+            iterations += 1;
+        }
+
+        iterations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        final_move::{FinalMoveSelection, SelectedBy},
+        limits::SearchLimits,
+        observer::SearchInfo,
+        rave::RaveSchedule,
+        state::tests::{quiet_mcts, TicTacToe, Turns, Worst},
+        Mcts,
+    };
+
+    #[test]
+    fn test_advance_root_keeps_subtree() {
+        let game = TicTacToe::default();
+        let mut searcher = quiet_mcts();
+
+        let cell = searcher.search(&SearchLimits::iterations(2000), game);
+        let tree = searcher.tree();
+        let child_id = tree.find_child(searcher.root_id().unwrap(), &cell).unwrap();
+        let child_sims = tree.nodes[child_id].num_sims();
+        let tree_size = tree.size();
+
+        assert!(searcher.advance_root(&[cell]));
+        assert_eq!(searcher.root_id(), Some(0));
+        assert_eq!(searcher.tree().nodes[0].num_sims(), child_sims);
+        assert!(searcher.tree().size() < tree_size);
+        assert!(searcher.tree().nodes[0].parent_id.is_none());
+    }
+
+    #[test]
+    fn test_search_after_advance_root() {
+        // without a hash key, the advanced root is trusted
+        let mut searcher = quiet_mcts();
+        let cell = searcher.search(&SearchLimits::iterations(2000), Turns(TicTacToe::default()));
+        let tree = searcher.tree();
+        let child_id = tree.find_child(searcher.root_id().unwrap(), &cell).unwrap();
+        let child_sims = tree.nodes[child_id].num_sims();
+
+        assert!(searcher.advance_root(&[cell]));
+        let game = Turns(TicTacToe::play(&[cell]));
+        searcher.search(&SearchLimits::iterations(100), game);
+        let root_id = searcher.root_id().unwrap();
+        assert_eq!(searcher.tree().nodes[root_id].num_sims(), child_sims + 100);
+    }
+
+    #[test]
+    fn test_search_restarts_from_new_position() {
+        let mut searcher = quiet_mcts();
+        searcher.search(&SearchLimits::iterations(500), TicTacToe::default());
+
+        // without advancing the root, the old tree doesn't match
+        let game = TicTacToe::play(&[0, 4]);
+        let cell = searcher.search(&SearchLimits::iterations(100), game);
+        assert!(![0, 4].contains(&cell));
+        assert_eq!(searcher.tree().nodes[0].num_sims(), 100);
+
+        // searching the same position again continues the tree
+        searcher.search(&SearchLimits::iterations(100), game);
+        assert_eq!(searcher.tree().nodes[0].num_sims(), 200);
+    }
+
+    #[test]
+    fn test_advance_root_without_tree() {
+        let mut searcher: Mcts<TicTacToe> = quiet_mcts();
+
+        assert!(!searcher.advance_root(&[4]));
+        assert_eq!(searcher.tree().size(), 0);
+    }
+
+    #[test]
+    fn test_parallel_search_consistent_stats() {
+        let mut searcher = quiet_mcts();
+        searcher.search_parallel(&SearchLimits::iterations(2000), TicTacToe::default(), 4);
+
+        let tree = searcher.tree();
+        let root = &tree.nodes[searcher.root_id().unwrap()];
+        let child_sims = root
+            .child_ids
+            .iter()
+            .map(|&child_id| tree.nodes[child_id].num_sims())
+            .sum::<usize>();
+
+        assert_eq!(root.num_sims(), 2000);
+        assert_eq!(root.num_sims(), child_sims);
+    }
+
+    #[test]
+    fn test_root_parallel_finds_win() {
+        let infos: Arc<Mutex<Vec<SearchInfo<usize>>>> = Arc::default();
+        let mut searcher = Mcts::default();
+        let observer_infos = infos.clone();
+        searcher.set_observer(Box::new(move |info: &SearchInfo<usize>| {
+            observer_infos.lock().unwrap().push(info.clone())
+        }));
+        // without the solver, the statistics of the trees decide
+        searcher.set_solver(false);
+
+        assert_eq!(
+            searcher.search_root_parallel(
+                &SearchLimits::iterations(2000),
+                TicTacToe::win_in_one(),
+                3
+            ),
+            2
+        );
+
+        let infos = infos.lock().unwrap();
+        let info = infos.last().unwrap();
+        assert_eq!(info.selected_by, Some(SelectedBy::RobustChild));
+        assert_eq!(info.thread_iterations, vec![2000; 3]);
+
+        // every tree hands all its root children's simulations to the merge
+        let merged_sims = info
+            .children
+            .iter()
+            .map(|child| child.num_sims)
+            .sum::<usize>();
+        let tree = searcher.tree();
+        let kept_sims = tree.nodes[0]
+            .child_ids
+            .iter()
+            .map(|&child_id| tree.nodes[child_id].num_sims())
+            .sum::<usize>();
+        assert_eq!(kept_sims, 2000);
+        assert_eq!(merged_sims, 3 * 2000);
+
+        let best = info
+            .children
+            .iter()
+            .max_by_key(|child| child.num_sims)
+            .unwrap();
+        assert_eq!(best.action, 2);
+        assert!(best.num_sims > 2000);
+    }
+
+    #[test]
+    fn test_search_limits() {
+        let game = TicTacToe::default();
+        let mut searcher = quiet_mcts();
+        searcher.search(&SearchLimits::iterations(500), game);
+
+        assert_eq!(searcher.tree().nodes[0].num_sims(), 500);
+
+        let limits = SearchLimits {
+            nodes: Some(800),
+            ..SearchLimits::iterations(5000)
+        };
+        searcher.search(&limits, game);
+
+        assert_eq!(searcher.tree().size(), 800);
+
+        let stop = Arc::new(AtomicBool::new(true));
+        let limits = SearchLimits {
+            stop: Some(stop),
+            ..Default::default()
+        };
+        searcher.reset();
+        searcher.search_parallel(&limits, game, 2);
+
+        assert_eq!(searcher.tree().nodes[0].num_sims(), 0);
+
+        searcher.reset();
+        assert_eq!(searcher.search_root_parallel(&limits, game, 2), 4);
+        assert_eq!(searcher.tree().nodes[0].num_sims(), 0);
+    }
+
+    #[test]
+    fn test_seeded_search_is_reproducible() {
+        let search = |seed| {
+            let mut searcher = quiet_mcts();
+            searcher.set_seed(seed);
+            searcher.set_transpositions(true);
+            searcher.set_rave(Some(RaveSchedule::default()));
+            let cell = searcher.search(&SearchLimits::iterations(2000), TicTacToe::default());
+
+            let nodes = searcher
+                .tree()
+                .nodes
+                .iter()
+                .map(|node| {
+                    let score = node.score().to_bits();
+                    (node.num_sims(), score, node.child_actions.clone())
+                })
+                .collect::<Vec<_>>();
+
+            (cell, nodes)
+        };
+
+        assert_eq!(search(7), search(7));
+        assert_ne!(search(7).1, search(8).1);
+    }
+
+    #[test]
+    fn test_max_robust_extends_search() {
+        let infos: Arc<Mutex<Vec<SearchInfo<usize>>>> = Arc::default();
+        let mut searcher = Mcts::default();
+        let observer_infos = infos.clone();
+        searcher.set_observer(Box::new(move |info: &SearchInfo<usize>| {
+            observer_infos.lock().unwrap().push(info.clone())
+        }));
+        searcher.set_final_move_selection(FinalMoveSelection::MaxRobustChild { extension: 0.5 });
+        searcher.search(&SearchLimits::iterations(2000), TicTacToe::default());
+
+        let infos = infos.lock().unwrap();
+        let info = infos.last().unwrap();
+        let root_sims = searcher.tree().nodes[0].num_sims();
+
+        assert_eq!(info.iterations, root_sims);
+        assert!((2000..=3000).contains(&root_sims));
+        match info.selected_by {
+            Some(SelectedBy::MaxRobustChild) => {}
+            Some(SelectedBy::RobustChild) => assert_eq!(root_sims, 3000),
+            other => panic!("unexpected criterion {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_max_robust_extension_keeps_limits() {
+        let mut searcher = Mcts::with_policy(Box::new(Worst));
+        searcher.set_solver(false);
+        searcher.set_final_move_selection(FinalMoveSelection::MaxRobustChild { extension: 1e6 });
+
+        // raised by the report after 100 iterations, the iteration it precedes still runs
+        let stop = Arc::new(AtomicBool::new(false));
+        let observer_stop = stop.clone();
+        searcher.set_observer(Box::new(move |info: &SearchInfo<usize>| {
+            if info.iterations >= 100 {
+                observer_stop.store(true, Ordering::Relaxed);
+            }
+        }));
+        searcher.set_report_interval(Some(Duration::ZERO));
+        let limits = SearchLimits {
+            iterations: Some(20),
+            stop: Some(stop),
+            ..Default::default()
+        };
+        searcher.search(&limits, TicTacToe::default());
+        assert_eq!(searcher.tree().nodes[0].num_sims(), 101);
+
+        // and at the time limit, with a wide margin for slow machines
+        searcher.reset();
+        searcher.set_observer(Box::new(|_: &SearchInfo<usize>| {}));
+        let limits = SearchLimits {
+            iterations: Some(20),
+            ..SearchLimits::millis(100)
+        };
+        let started = Instant::now();
+        searcher.search(&limits, TicTacToe::default());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use crate::state::State;
+
+/// Heuristic value of a state in `[0, 1]`, from the perspective of
+/// the player who moved into it
+pub trait Evaluator<T>: Send + Sync
+where
+    T: State,
+{
+    fn evaluate(&self, state: &T) -> f32;
+}
+
+impl<T, F> Evaluator<T> for F
+where
+    T: State,
+    F: Fn(&T) -> f32 + Send + Sync,
+{
+    fn evaluate(&self, state: &T) -> f32 {
+        self(state)
+    }
+}
+
+/// Implicit minimax backups (MCTS-IMB): every node is evaluated when it's
+/// added, and keeps the minimax value of the evaluations of its expanded
+/// descendants. Selection uses `(1 - weight) * mean + weight * minimax`.
+/// Terminal states use their reward instead of the evaluator. Only
+/// supported for two players taking turns
+pub struct ImplicitMinimax<T>
+where
+    T: State,
+{
+    pub weight: f32,
+    evaluator: Arc<dyn Evaluator<T>>,
+}
+
+impl<T> Clone for ImplicitMinimax<T>
+where
+    T: State,
+{
+    fn clone(&self) -> Self {
+        ImplicitMinimax {
+            weight: self.weight,
+            evaluator: self.evaluator.clone(),
+        }
+    }
+}
+
+impl<T> ImplicitMinimax<T>
+where
+    T: State,
+{
+    pub fn new(weight: f32, evaluator: impl Evaluator<T> + 'static) -> Self {
+        ImplicitMinimax {
+            weight,
+            evaluator: Arc::new(evaluator),
+        }
+    }
+
+    pub fn evaluate(&self, state: &T) -> f32 {
+        self.evaluator.evaluate(state)
+    }
+
+    /// Blend the mean of a node with its minimax value
+    pub fn blend(&self, mean: f32, num_sims: usize, minimax: f32) -> f32 {
+        if num_sims == 0 {
+            return minimax;
+        }
+
+        (1. - self.weight) * mean + self.weight * minimax
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::ImplicitMinimax;
+    use crate::{
+        limits::SearchLimits,
+        state::tests::{quiet_mcts, TicTacToe},
+    };
+
+    #[test]
+    fn test_implicit_minimax_backs_up_evaluations() {
+        let evaluations = Arc::new(AtomicUsize::new(0));
+        let counter = evaluations.clone();
+        let mut searcher = quiet_mcts();
+        searcher.set_solver(false);
+        searcher.set_implicit_minimax(Some(ImplicitMinimax::new(0.5, move |_: &TicTacToe| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            0.5
+        })));
+
+        assert_eq!(
+            searcher.search(&SearchLimits::iterations(200), TicTacToe::win_in_one()),
+            2
+        );
+
+        let tree = searcher.tree();
+        let root_id = searcher.root_id().unwrap();
+        let win_id = tree.find_child(root_id, &2).unwrap();
+        assert!(evaluations.load(Ordering::Relaxed) > 0);
+        assert_eq!(tree.nodes[win_id].minimax_value(), 1.);
+        // the player to move at the root can win right away
+        assert_eq!(tree.nodes[root_id].minimax_value(), 0.);
+    }
+}
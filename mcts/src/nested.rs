@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+
+use fastrand::Rng;
+
+use crate::{single_player::SinglePlayer, state::sample_weighted};
+
+/// Best complete action sequence found, with its score
+pub type Sequence<A> = (f32, Vec<A>);
+
+/// Play random actions until reaching a terminal state
+fn playout<T>(from: &T, mut actions: Vec<T::Action>, rng: &mut Rng) -> Sequence<T::Action>
+where
+    T: SinglePlayer + Clone,
+{
+    let mut state = from.clone();
+    while !state.is_terminal(actions.len()) {
+        let action = state.possible_actions(rng).pop().unwrap();
+        state = state.apply_action(action.clone());
+        actions.push(action);
+    }
+
+    (state.score(), actions)
+}
+
+fn weight(policy: &HashMap<u64, f32>, code: u64) -> f32 {
+    policy.get(&code).copied().unwrap_or(0.)
+}
+
+/// Nested Monte Carlo Search. At level 0 a search is a random playout, at
+/// level `n` every action of the current state is tried with a search of
+/// level `n - 1`, and the best sequence found so far is followed one action
+/// further until the end
+pub struct Nmcs {
+    pub level: usize,
+    rng: Rng,
+}
+
+impl Default for Nmcs {
+    fn default() -> Self {
+        Nmcs {
+            level: 2,
+            rng: Rng::new(),
+        }
+    }
+}
+
+impl Nmcs {
+    pub fn new(level: usize) -> Self {
+        Nmcs {
+            level,
+            ..Default::default()
+        }
+    }
+
+    /// See [`Mcts::set_seed`](crate::Mcts::set_seed)
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::with_seed(seed);
+    }
+
+    pub fn search<T>(&mut self, root_state: T) -> Sequence<T::Action>
+    where
+        T: SinglePlayer + Clone,
+    {
+        self.nested(&root_state, vec![], self.level)
+    }
+
+    /// `played` are the actions leading from the root to `state`
+    fn nested<T>(&mut self, state: &T, played: Vec<T::Action>, level: usize) -> Sequence<T::Action>
+    where
+        T: SinglePlayer + Clone,
+    {
+        if level == 0 {
+            return playout(state, played, &mut self.rng);
+        }
+
+        let mut state = state.clone();
+        let mut played = played;
+        let mut best: Option<Sequence<T::Action>> = None;
+
+        while !state.is_terminal(played.len()) {
+            for action in state.possible_actions(&mut self.rng) {
+                let mut actions = played.clone();
+                actions.push(action.clone());
+
+                let child = state.apply_action(action);
+                let (score, actions) = self.nested(&child, actions, level - 1);
+                if best.as_ref().is_none_or(|(best, _)| score > *best) {
+                    best = Some((score, actions));
+                }
+            }
+
+            // follow the best sequence one action further
+            let action = best.as_ref().unwrap().1[played.len()].clone();
+            state = state.apply_action(action.clone());
+            played.push(action);
+        }
+
+        best.unwrap_or_else(|| (state.score(), played))
+    }
+}
+
+/// Nested Rollout Policy Adaptation. Playouts choose actions with a softmax
+/// over learned weights, a search of level `n` runs `iterations` searches
+/// of level `n - 1` and after each one moves the weights towards the best
+/// sequence found so far. Weights are learned per [`SinglePlayer::action_code`]
+pub struct Nrpa {
+    pub level: usize,
+    /// Searches of the level below per search
+    pub iterations: usize,
+    /// Learning rate of the policy
+    pub alpha: f32,
+    rng: Rng,
+}
+
+impl Default for Nrpa {
+    fn default() -> Self {
+        Nrpa {
+            level: 2,
+            iterations: 100,
+            alpha: 1.,
+            rng: Rng::new(),
+        }
+    }
+}
+
+impl Nrpa {
+    pub fn new(level: usize, iterations: usize) -> Self {
+        Nrpa {
+            level,
+            iterations,
+            ..Default::default()
+        }
+    }
+
+    /// See [`Mcts::set_seed`](crate::Mcts::set_seed)
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::with_seed(seed);
+    }
+
+    pub fn search<T>(&mut self, root_state: T) -> Sequence<T::Action>
+    where
+        T: SinglePlayer + Clone,
+    {
+        self.nested(&root_state, self.level, HashMap::new())
+    }
+
+    fn nested<T>(
+        &mut self,
+        root_state: &T,
+        level: usize,
+        mut policy: HashMap<u64, f32>,
+    ) -> Sequence<T::Action>
+    where
+        T: SinglePlayer + Clone,
+    {
+        if level == 0 {
+            return self.playout(root_state, &policy);
+        }
+
+        let mut best: Option<Sequence<T::Action>> = None;
+        for _ in 0..self.iterations {
+            let (score, actions) = self.nested(root_state, level - 1, policy.clone());
+            if best.as_ref().is_none_or(|(best, _)| score >= *best) {
+                best = Some((score, actions));
+            }
+
+            policy = self.adapt(root_state, policy, &best.as_ref().unwrap().1);
+        }
+
+        best.unwrap()
+    }
+
+    /// Random playout, sampling actions by the exponent of their weight
+    fn playout<T>(&mut self, root_state: &T, policy: &HashMap<u64, f32>) -> Sequence<T::Action>
+    where
+        T: SinglePlayer + Clone,
+    {
+        let mut state = root_state.clone();
+        let mut actions = vec![];
+
+        while !state.is_terminal(actions.len()) {
+            let mut legal = state.possible_actions(&mut self.rng);
+            let weights = legal
+                .iter()
+                .map(|action| weight(policy, state.action_code(action)).exp());
+            let i = sample_weighted(weights, &mut self.rng);
+
+            let action = legal.swap_remove(i);
+            state = state.apply_action(action.clone());
+            actions.push(action);
+        }
+
+        (state.score(), actions)
+    }
+
+    /// Increase the weights of the actions in `sequence`, and decrease
+    /// those of the other legal actions by their probability
+    fn adapt<T>(
+        &mut self,
+        root_state: &T,
+        policy: HashMap<u64, f32>,
+        sequence: &[T::Action],
+    ) -> HashMap<u64, f32>
+    where
+        T: SinglePlayer + Clone,
+    {
+        let mut adapted = policy.clone();
+        let mut state = root_state.clone();
+
+        for action in sequence {
+            let legal = state.possible_actions(&mut self.rng);
+            let weights = legal
+                .iter()
+                .map(|action| weight(&policy, state.action_code(action)).exp())
+                .collect::<Vec<_>>();
+            let total = weights.iter().sum::<f32>();
+
+            *adapted.entry(state.action_code(action)).or_default() += self.alpha;
+            for (other, weight) in legal.iter().zip(weights) {
+                *adapted.entry(state.action_code(other)).or_default() -=
+                    self.alpha * weight / total;
+            }
+
+            state = state.apply_action(action.clone());
+        }
+
+        adapted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Nmcs, Nrpa};
+    use crate::single_player::tests::Bits;
+
+    #[test]
+    fn test_nested_searches_find_best_sequence() {
+        let best = (16., vec![true, false, true, false, false, true]);
+
+        let mut nmcs = Nmcs::new(2);
+        nmcs.set_seed(1);
+        assert_eq!(nmcs.search(Bits(vec![])), best);
+
+        let mut nrpa = Nrpa::new(2, 20);
+        nrpa.set_seed(1);
+        assert_eq!(nrpa.search(Bits(vec![])), best);
+    }
+}
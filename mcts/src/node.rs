@@ -0,0 +1,308 @@
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+use fastrand::Rng;
+
+use crate::{atomic::AtomicF32, state::State};
+
+/// Game theoretic value of a node, from the perspective of the player
+/// who moved into it. The depth is the number of plies until the game
+/// ends with optimal play
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Proof {
+    Win(u32),
+    Loss(u32),
+    Draw,
+}
+
+impl Proof {
+    const WIN: u32 = 1 << 30;
+    const LOSS: u32 = 2 << 30;
+    const DRAW: u32 = 3 << 30;
+    const DEPTH_MASK: u32 = (1 << 30) - 1;
+
+    /// The value of a terminal state, from its reward
+    pub fn from_reward(reward: f32) -> Self {
+        if reward >= 1. {
+            Proof::Win(0)
+        } else if reward <= 0. {
+            Proof::Loss(0)
+        } else {
+            Proof::Draw
+        }
+    }
+
+    pub fn reward(self) -> f32 {
+        match self {
+            Proof::Win(_) => 1.,
+            Proof::Loss(_) => 0.,
+            Proof::Draw => 0.5,
+        }
+    }
+
+    /// The same result, seen by the other player
+    pub fn invert(self) -> Self {
+        match self {
+            Proof::Win(depth) => Proof::Loss(depth),
+            Proof::Loss(depth) => Proof::Win(depth),
+            Proof::Draw => Proof::Draw,
+        }
+    }
+
+    fn encode(proof: Option<Proof>) -> u32 {
+        match proof {
+            None => 0,
+            Some(Proof::Win(depth)) => Proof::WIN | depth,
+            Some(Proof::Loss(depth)) => Proof::LOSS | depth,
+            Some(Proof::Draw) => Proof::DRAW,
+        }
+    }
+
+    fn decode(bits: u32) -> Option<Proof> {
+        let depth = bits & Proof::DEPTH_MASK;
+
+        match bits & !Proof::DEPTH_MASK {
+            Proof::WIN => Some(Proof::Win(depth)),
+            Proof::LOSS => Some(Proof::Loss(depth)),
+            Proof::DRAW => Some(Proof::Draw),
+            _ => None,
+        }
+    }
+}
+
+/// Nodes contain the required data to create a tree,
+/// as well as parameters used by the MCTS algorithm.
+///
+/// The statistics are atomics, so they can be updated by
+/// multiple threads sharing a single tree
+pub struct Node<T>
+where
+    T: State,
+{
+    pub state: T,
+    pub id: usize,
+    pub parent_id: Option<usize>,
+    pub child_ids: Vec<usize>,
+    /// The action leading to each child in `child_ids`
+    pub child_actions: Vec<T::Action>,
+    /// Untried actions, the most promising one is last
+    pub actions: Vec<T::Action>,
+    /// Normalized prior of every action in `actions`
+    pub action_priors: Vec<f32>,
+    /// Set when the tree detects transpositions
+    pub hash: Option<u64>,
+    /// Prior probability of selecting this node, used by PUCT
+    pub prior: f32,
+    /// The player who moved into this node, for games
+    /// implementing [`State::current_player`]
+    pub player: Option<usize>,
+
+    terminal: bool,
+    /// See [`State::chance_outcomes`]
+    chance: bool,
+    proof: AtomicU32,
+    num_sims: AtomicUsize,
+    virtual_loss: AtomicUsize,
+    score: AtomicF32,
+    score_sq: AtomicF32,
+    amaf_sims: AtomicUsize,
+    amaf_score: AtomicF32,
+    /// See [`ImplicitMinimax`](crate::minimax::ImplicitMinimax)
+    minimax: AtomicF32,
+}
+
+impl<T> Node<T>
+where
+    T: State,
+{
+    pub fn new(mut state: T, id: usize, parent_id: Option<usize>, rng: &mut Rng) -> Self {
+        let terminal = state.is_terminal(0);
+        let outcomes = state.chance_outcomes();
+        let chance = outcomes.is_some();
+
+        // the priors of chance outcomes are their probabilities
+        let mut actions = match outcomes {
+            Some(outcomes) => outcomes
+                .into_iter()
+                .map(|(outcome, probability)| (probability, outcome))
+                .collect::<Vec<_>>(),
+            None => state
+                .possible_actions(rng)
+                .into_iter()
+                .map(|action| (state.action_prior(&action), action))
+                .collect::<Vec<_>>(),
+        };
+        // stable, so equal priors keep their shuffled order
+        actions.sort_by(|(x, _), (y, _)| x.total_cmp(y));
+
+        // priors that don't sum to anything positive fall back to uniform
+        let prior_sum = actions.iter().map(|(prior, _)| prior).sum::<f32>();
+        let uniform = !(prior_sum > 0. && prior_sum.is_finite());
+        let num_actions = actions.len() as f32;
+        let (action_priors, actions) = actions
+            .into_iter()
+            .map(|(prior, action)| match uniform {
+                true => (1. / num_actions, action),
+                false => (prior / prior_sum, action),
+            })
+            .unzip();
+
+        Node {
+            state,
+            id,
+            parent_id,
+            child_ids: vec![],
+            child_actions: vec![],
+            actions,
+            action_priors,
+            hash: None,
+            prior: 1.,
+            player: None,
+            terminal,
+            chance,
+            proof: AtomicU32::new(0),
+            num_sims: AtomicUsize::new(0),
+            virtual_loss: AtomicUsize::new(0),
+            score: AtomicF32::new(0.),
+            score_sq: AtomicF32::new(0.),
+            amaf_sims: AtomicUsize::new(0),
+            amaf_score: AtomicF32::new(0.),
+            minimax: AtomicF32::new(0.5),
+        }
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        self.terminal
+    }
+
+    /// Whether the children of this node are the outcomes of a chance event
+    pub fn is_chance(&self) -> bool {
+        self.chance
+    }
+
+    pub fn proof(&self) -> Option<Proof> {
+        Proof::decode(self.proof.load(Ordering::Relaxed))
+    }
+
+    pub fn set_proof(&self, proof: Proof) {
+        self.proof
+            .store(Proof::encode(Some(proof)), Ordering::Relaxed);
+    }
+
+    pub fn is_explored(&self) -> bool {
+        self.num_sims() != 0
+    }
+
+    /// Number of simulations, counting pending virtual losses
+    /// as simulations with a reward of 0
+    pub fn num_sims(&self) -> usize {
+        self.num_sims.load(Ordering::Relaxed) + self.virtual_loss.load(Ordering::Relaxed)
+    }
+
+    pub fn score(&self) -> f32 {
+        self.score.load()
+    }
+
+    pub fn mean_score(&self) -> f32 {
+        self.score() / self.num_sims() as f32
+    }
+
+    /// Sample variance of the rewards backpropagated through this node
+    pub fn variance(&self) -> f32 {
+        let mean = self.mean_score();
+        (self.score_sq.load() / self.num_sims() as f32 - mean * mean).max(0.)
+    }
+
+    pub fn update(&self, reward: f32) {
+        self.num_sims.fetch_add(1, Ordering::Relaxed);
+        self.score.fetch_add(reward);
+        self.score_sq.fetch_add(reward * reward);
+    }
+
+    pub fn amaf_sims(&self) -> usize {
+        self.amaf_sims.load(Ordering::Relaxed)
+    }
+
+    pub fn amaf_mean_score(&self) -> f32 {
+        self.amaf_score.load() / self.amaf_sims() as f32
+    }
+
+    /// Update the All-Moves-As-First statistics, for simulations that
+    /// played this node's action later on instead of right away
+    pub fn update_amaf(&self, reward: f32) {
+        self.amaf_sims.fetch_add(1, Ordering::Relaxed);
+        self.amaf_score.fetch_add(reward);
+    }
+
+    /// Minimax value of the heuristic evaluations below this node, from
+    /// the perspective of the player who moved into it
+    pub fn minimax_value(&self) -> f32 {
+        self.minimax.load()
+    }
+
+    pub fn set_minimax_value(&self, value: f32) {
+        self.minimax.store(value);
+    }
+
+    /// Make this node look worse to other threads while one
+    /// of its simulations is still running
+    pub fn add_virtual_loss(&self) {
+        self.virtual_loss.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn revert_virtual_loss(&self) {
+        self.virtual_loss.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fastrand::Rng;
+
+    use super::Node;
+    use crate::state::{tests::TicTacToe, State};
+
+    /// [`TicTacToe`] without any prior on its moves
+    #[derive(Clone, Copy)]
+    struct NoPriors(TicTacToe);
+
+    impl State for NoPriors {
+        type Action = usize;
+
+        fn possible_actions(&self, rng: &mut Rng) -> Vec<Self::Action> {
+            self.0.possible_actions(rng)
+        }
+
+        fn apply_action(&self, action: Self::Action) -> Self {
+            NoPriors(self.0.apply_action(action))
+        }
+
+        fn last_action(&self) -> Option<Self::Action> {
+            self.0.last_action()
+        }
+
+        fn reward(&self, perspective: &Self) -> f32 {
+            self.0.reward(&perspective.0)
+        }
+
+        fn is_terminal(&mut self, depth: usize) -> bool {
+            self.0.is_terminal(depth)
+        }
+
+        fn action_prior(&self, _action: &Self::Action) -> f32 {
+            0.
+        }
+    }
+
+    #[test]
+    fn test_zero_priors_fall_back_to_uniform() {
+        let node = Node::new(
+            NoPriors(TicTacToe::default()),
+            0,
+            None,
+            &mut Rng::with_seed(0),
+        );
+
+        assert_eq!(node.actions.len(), 9);
+        assert!(node.action_priors.iter().all(|&prior| prior == 1. / 9.));
+    }
+}
@@ -0,0 +1,217 @@
+use std::{fmt::Display, time::Duration};
+
+use crate::{final_move::SelectedBy, node::Proof};
+
+/// Statistics of one of the root's children
+#[derive(Clone, Debug)]
+pub struct ChildInfo<A> {
+    pub action: A,
+    pub num_sims: usize,
+    /// From the perspective of the player to move at the root
+    pub mean_score: f32,
+    /// Not every tree keeps track of the variance
+    pub std_dev: Option<f32>,
+    pub proof: Option<Proof>,
+}
+
+/// Snapshot of a running or finished search
+#[derive(Clone, Debug)]
+pub struct SearchInfo<A> {
+    pub iterations: usize,
+    pub elapsed: Duration,
+    /// Number of nodes in the tree
+    pub nodes: usize,
+    /// Estimated memory usage of the tree in bytes
+    pub memory: usize,
+    /// Length of the principal variation
+    pub depth: usize,
+    /// The action the search would play if it were stopped now
+    pub best_action: Option<A>,
+    /// Mean score of the best action, from the perspective of the
+    /// player to move at the root
+    pub value: Option<f32>,
+    /// Proven result of the root, from the perspective of the player to move
+    pub proof: Option<Proof>,
+    /// Most visited line from the root
+    pub pv: Vec<A>,
+    pub children: Vec<ChildInfo<A>>,
+    /// The criterion that chose `best_action`
+    pub selected_by: Option<SelectedBy>,
+    /// Iterations per worker thread or tree, empty for single threaded searches
+    pub thread_iterations: Vec<usize>,
+    /// Whether this is the final report of the search
+    pub finished: bool,
+}
+
+impl<A> SearchInfo<A> {
+    pub fn iterations_per_sec(&self) -> f32 {
+        self.iterations as f32 / self.elapsed.as_secs_f32()
+    }
+}
+
+/// Receives [`SearchInfo`] reports while searching, see
+/// [`Mcts::set_report_interval`](crate::Mcts::set_report_interval),
+/// and once when the search finishes
+pub trait SearchObserver<A>: Send {
+    fn on_info(&mut self, info: &SearchInfo<A>);
+}
+
+impl<A, F> SearchObserver<A> for F
+where
+    F: FnMut(&SearchInfo<A>) + Send,
+{
+    fn on_info(&mut self, info: &SearchInfo<A>) {
+        self(info)
+    }
+}
+
+/// Prints a line per periodic report and the root's children, speed,
+/// tree size, proven result and principal variation once finished
+#[derive(Clone, Copy, Default)]
+pub struct ConsoleObserver;
+
+impl<A> SearchObserver<A> for ConsoleObserver
+where
+    A: Display,
+{
+    fn on_info(&mut self, info: &SearchInfo<A>) {
+        if !info.finished {
+            print!(
+                "{} its\t{} nodes\tdepth {}",
+                info.iterations, info.nodes, info.depth
+            );
+            if let (Some(action), Some(value)) = (&info.best_action, info.value) {
+                print!("\tbest {action}\tvalue {value}");
+            }
+            println!();
+            return;
+        }
+
+        for child in &info.children {
+            print!(
+                "{}:\t{}\t{}",
+                child.action, child.num_sims, child.mean_score
+            );
+            if let Some(std_dev) = child.std_dev {
+                print!("\t{std_dev}");
+            }
+            println!();
+        }
+
+        for (i, iterations) in info.thread_iterations.iter().enumerate() {
+            println!(
+                "thread {i}: {} its/sec",
+                *iterations as f32 / info.elapsed.as_secs_f32()
+            );
+        }
+
+        if let (Some(action), Some(selected_by)) = (&info.best_action, info.selected_by) {
+            println!("best: {action} ({selected_by})");
+        }
+        println!("{} its/sec", info.iterations_per_sec());
+        println!(
+            "tree size: {}\t{} bytes/node",
+            info.nodes,
+            info.memory / info.nodes.max(1)
+        );
+        match info.proof {
+            Some(Proof::Win(plies)) => println!("forced win in {}", plies.div_ceil(2)),
+            Some(Proof::Loss(plies)) => println!("forced loss in {}", plies / 2),
+            Some(Proof::Draw) => println!("forced draw"),
+            None => {}
+        }
+        println!("continuation: ");
+        for action in &info.pv {
+            print!("{action}, ");
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::SearchInfo;
+    use crate::{
+        final_move::FinalMoveSelection,
+        limits::SearchLimits,
+        state::tests::{TicTacToe, Worst},
+        Mcts,
+    };
+
+    #[test]
+    fn test_observer_receives_reports() {
+        let infos: Arc<Mutex<Vec<SearchInfo<usize>>>> = Arc::default();
+        let mut searcher = Mcts::default();
+        let observer_infos = infos.clone();
+        searcher.set_observer(Box::new(move |info: &SearchInfo<usize>| {
+            observer_infos.lock().unwrap().push(info.clone())
+        }));
+        searcher.set_report_interval(Some(Duration::ZERO));
+
+        let cell = searcher.search(&SearchLimits::iterations(200), TicTacToe::default());
+
+        let infos = infos.lock().unwrap();
+        let last = infos.last().unwrap();
+
+        assert_eq!(infos.len(), 201);
+        assert!(infos[..200].iter().all(|info| !info.finished));
+        assert!(last.finished);
+        assert_eq!(last.iterations, 200);
+        assert_eq!(last.best_action, Some(cell));
+        assert_eq!(last.pv[0], cell);
+        assert_eq!(
+            last.children
+                .iter()
+                .map(|child| child.num_sims)
+                .sum::<usize>(),
+            200
+        );
+    }
+
+    /// Have `searcher` report every iteration into the returned list
+    fn record_reports(searcher: &mut Mcts<TicTacToe>) -> Arc<Mutex<Vec<SearchInfo<usize>>>> {
+        let infos: Arc<Mutex<Vec<SearchInfo<usize>>>> = Arc::default();
+        let observer_infos = infos.clone();
+        searcher.set_observer(Box::new(move |info: &SearchInfo<usize>| {
+            observer_infos.lock().unwrap().push(info.clone())
+        }));
+        searcher.set_report_interval(Some(Duration::ZERO));
+
+        infos
+    }
+
+    #[test]
+    fn test_observer_reports_extension_and_root_parallel() {
+        let mut searcher = Mcts::with_policy(Box::new(Worst));
+        let infos = record_reports(&mut searcher);
+        searcher.set_solver(false);
+        searcher.set_final_move_selection(FinalMoveSelection::MaxRobustChild { extension: 2. });
+        searcher.search(&SearchLimits::iterations(100), TicTacToe::default());
+
+        // the extension keeps counting from where the search left off
+        let infos = infos.lock().unwrap();
+        let (last, reports) = infos.split_last().unwrap();
+        assert_eq!(last.iterations, 300);
+        assert_eq!(reports.len(), 300);
+        assert!(reports
+            .iter()
+            .enumerate()
+            .all(|(i, info)| info.iterations == i));
+
+        let mut searcher = Mcts::default();
+        let infos = record_reports(&mut searcher);
+        searcher.search_root_parallel(&SearchLimits::iterations(200), TicTacToe::default(), 3);
+
+        // only the first tree reports while searching
+        let infos = infos.lock().unwrap();
+        let (last, reports) = infos.split_last().unwrap();
+        assert_eq!(last.iterations, 600);
+        assert_eq!(reports.len(), 200);
+        assert!(reports.iter().all(|info| !info.finished));
+    }
+}
@@ -0,0 +1,451 @@
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use fastrand::Rng;
+
+use crate::{
+    driver::{ActionTree, Reporter, SearchDriver},
+    final_move::{FinalMoveSelection, SelectedBy},
+    limits::SearchLimits,
+    mcts::Mcts,
+    observer::{SearchInfo, SearchObserver},
+    state::{sample_weighted, State},
+};
+
+/// Node of an [`OpenLoopTree`], identified by the actions leading to it
+/// from the root rather than by a state
+pub struct OpenLoopNode<A> {
+    /// `None` for the root
+    pub action: Option<A>,
+    pub child_ids: Vec<usize>,
+    num_sims: usize,
+    score: f32,
+    /// Number of times this node's action was legal when its parent
+    /// was visited, used instead of the parent's simulations by UCB1
+    availability: usize,
+}
+
+impl<A> OpenLoopNode<A> {
+    fn new(action: Option<A>) -> Self {
+        OpenLoopNode {
+            action,
+            child_ids: vec![],
+            num_sims: 0,
+            score: 0.,
+            availability: 0,
+        }
+    }
+
+    pub fn num_sims(&self) -> usize {
+        self.num_sims
+    }
+
+    pub fn score(&self) -> f32 {
+        self.score
+    }
+
+    pub fn mean_score(&self) -> f32 {
+        self.score / self.num_sims as f32
+    }
+
+    pub fn availability(&self) -> usize {
+        self.availability
+    }
+}
+
+/// Open-loop tree for games where applying an action can have random
+/// outcomes, see [`State::apply_action_with`]. Nodes don't store states:
+/// every iteration replays the actions from the root, sampling a new
+/// outcome for each of them, so the statistics of a node average over all
+/// states its action sequence can lead to. Actions that aren't legal in
+/// the sampled state are skipped during selection.
+///
+/// Selection is UCB1, using how often a child was available rather than
+/// the parent's number of simulations. Only two players taking turns
+/// are supported
+pub struct OpenLoopTree<T>
+where
+    T: State,
+{
+    pub nodes: Vec<OpenLoopNode<T::Action>>,
+    root_state: Option<T>,
+    /// Exploration constant of UCB1
+    pub c: f32,
+    final_move: FinalMoveSelection,
+    rng: Rng,
+}
+
+impl<T> Default for OpenLoopTree<T>
+where
+    T: State,
+{
+    fn default() -> Self {
+        OpenLoopTree {
+            nodes: vec![],
+            root_state: None,
+            c: 1.,
+            final_move: FinalMoveSelection::default(),
+            rng: Rng::new(),
+        }
+    }
+}
+
+impl<T> OpenLoopTree<T>
+where
+    T: State + Clone,
+{
+    /// Throw away the tree and start over from `state`. Panics for states
+    /// implementing [`State::current_player`], only two players taking
+    /// turns are supported
+    pub fn set_root(&mut self, state: T) {
+        assert!(
+            state.current_player().is_none(),
+            "only two players taking turns are supported, not states with a current player"
+        );
+
+        self.nodes.clear();
+        self.nodes.push(OpenLoopNode::new(None));
+        self.root_state = Some(state);
+    }
+
+    /// See [`Tree::set_seed`](crate::tree::Tree::set_seed)
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::with_seed(seed);
+    }
+
+    /// A new generator seeded from this tree's generator
+    pub fn fork_rng(&mut self) -> Rng {
+        Rng::with_seed(self.rng.u64(..))
+    }
+
+    pub fn set_final_move_selection(&mut self, selection: FinalMoveSelection) {
+        self.final_move = selection;
+    }
+
+    /// Replay actions from the root, sampling their outcomes, until reaching
+    /// a terminal state or a legal action without a node, which gets added.
+    /// Returns the path of node ids together with the last sampled state
+    pub fn select_expand(&mut self) -> (Vec<usize>, T) {
+        let mut state = self.root_state.clone().unwrap();
+        let mut path = vec![0];
+        let mut node_id = 0;
+
+        while !state.is_terminal(0) {
+            // chance events aren't part of the tree, their
+            // outcomes are averaged like random action outcomes
+            if let Some(mut outcomes) = state.chance_outcomes() {
+                let i = sample_weighted(outcomes.iter().map(|(_, p)| *p), &mut self.rng);
+                state = state.apply_action_with(outcomes.swap_remove(i).0, &mut self.rng);
+                continue;
+            }
+
+            let mut actions = state.possible_actions(&mut self.rng);
+            let mut available = vec![];
+
+            for &child_id in &self.nodes[node_id].child_ids {
+                let action = self.nodes[child_id].action.as_ref().unwrap();
+                if let Some(i) = actions.iter().position(|other| other == action) {
+                    actions.swap_remove(i);
+                    available.push(child_id);
+                }
+            }
+
+            for &child_id in &available {
+                self.nodes[child_id].availability += 1;
+            }
+
+            // actions were shuffled, any untried one will do
+            if let Some(action) = actions.pop() {
+                let child_id = self.nodes.len();
+                let mut child = OpenLoopNode::new(Some(action.clone()));
+                child.availability = 1;
+                self.nodes.push(child);
+                self.nodes[node_id].child_ids.push(child_id);
+
+                path.push(child_id);
+                return (path, state.apply_action_with(action, &mut self.rng));
+            }
+
+            let Some(child_id) = self.select_child(&available) else {
+                break;
+            };
+
+            let action = self.nodes[child_id].action.clone().unwrap();
+            state = state.apply_action_with(action, &mut self.rng);
+            path.push(child_id);
+            node_id = child_id;
+        }
+
+        (path, state)
+    }
+
+    /// UCB1 over the children that are legal in the sampled state
+    fn select_child(&self, available: &[usize]) -> Option<usize> {
+        select_available(available, self.c, |child_id| {
+            let child = &self.nodes[child_id];
+            (child.num_sims, child.mean_score(), child.availability)
+        })
+    }
+
+    /// `reward` is from the perspective of the player who moved
+    /// into the last node of the path
+    pub fn backpropagate(&mut self, path: &[usize], reward: f32) {
+        let mut node_reward = reward;
+        for &id in path.iter().rev() {
+            let node = &mut self.nodes[id];
+            node.num_sims += 1;
+            node.score += node_reward;
+
+            node_reward = 1. - node_reward;
+        }
+    }
+
+    /// The root action chosen by the final move selection, along with the
+    /// criterion that chose it. `None` when the root state has no actions
+    pub fn final_action(&self) -> Option<(T::Action, SelectedBy)> {
+        let root_state = self.root_state.as_ref().unwrap();
+        self.select_root_action(self.final_move, root_state, &self.rng)
+    }
+
+    /// Panics when the root state has no actions
+    pub fn best_action(&self) -> T::Action {
+        self.final_action()
+            .expect("the root state has no actions")
+            .0
+    }
+}
+
+/// UCB1 using how often a child was available instead of its parent's
+/// simulations, unexplored children come first. `stats` gives the
+/// simulations, mean score and availability of a child
+pub(crate) fn select_available(
+    available: &[usize],
+    c: f32,
+    stats: impl Fn(usize) -> (usize, f32, usize),
+) -> Option<usize> {
+    let mut best = None;
+    let mut best_score = f32::NEG_INFINITY;
+
+    for &child_id in available {
+        let (num_sims, mean_score, availability) = stats(child_id);
+        if num_sims == 0 {
+            return Some(child_id);
+        }
+
+        let log_n = (availability as f32).ln();
+        let score = mean_score + c * (2. * log_n / num_sims as f32).sqrt();
+        if best.is_none() || score > best_score {
+            best = Some(child_id);
+            best_score = score;
+        }
+    }
+
+    best
+}
+
+impl<T> ActionTree for OpenLoopTree<T>
+where
+    T: State,
+{
+    type Action = T::Action;
+
+    fn children(&self, node_id: usize) -> impl Iterator<Item = usize> + '_ {
+        self.nodes[node_id].child_ids.iter().copied()
+    }
+
+    fn action(&self, node_id: usize) -> &T::Action {
+        self.nodes[node_id].action.as_ref().unwrap()
+    }
+
+    fn num_sims(&self, node_id: usize) -> usize {
+        self.nodes[node_id].num_sims
+    }
+
+    fn mean_score(&self, node_id: usize) -> f32 {
+        self.nodes[node_id].mean_score()
+    }
+}
+
+impl<T> SearchDriver for OpenLoopTree<T>
+where
+    T: State + Clone,
+    T::Action: Display,
+{
+    type Action = T::Action;
+
+    fn iterate(&mut self) {
+        let (path, state) = self.select_expand();
+        let reward = Mcts::rollout(&state, &mut self.rng).two_player();
+        self.backpropagate(&path, reward);
+    }
+
+    fn size(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Estimate of the memory used by the nodes in bytes
+    fn memory_usage(&self) -> usize {
+        self.nodes
+            .iter()
+            .map(|node| {
+                std::mem::size_of::<OpenLoopNode<T::Action>>()
+                    + node.child_ids.capacity() * std::mem::size_of::<usize>()
+            })
+            .sum()
+    }
+
+    fn search_info(&self, iterations: usize, elapsed: Duration) -> SearchInfo<T::Action> {
+        let selected = self
+            .final_action()
+            .filter(|_| !self.nodes[0].child_ids.is_empty());
+
+        SearchInfo {
+            iterations,
+            elapsed,
+            nodes: self.size(),
+            memory: self.memory_usage(),
+            ..self.root_info(selected)
+        }
+    }
+}
+
+/// Searcher using an [`OpenLoopTree`], every search starts from scratch
+pub struct OpenLoopMcts<T>
+where
+    T: State,
+{
+    tree: OpenLoopTree<T>,
+    reporter: Reporter<T::Action>,
+}
+
+impl<T> Default for OpenLoopMcts<T>
+where
+    T: State,
+{
+    fn default() -> Self {
+        OpenLoopMcts {
+            tree: OpenLoopTree::default(),
+            reporter: Reporter::default(),
+        }
+    }
+}
+
+impl<T> OpenLoopMcts<T>
+where
+    T: State + Clone,
+    T::Action: Display,
+{
+    pub fn tree(&self) -> &OpenLoopTree<T> {
+        &self.tree
+    }
+
+    /// See [`Mcts::set_seed`]
+    pub fn set_seed(&mut self, seed: u64) {
+        self.tree.set_seed(seed);
+    }
+
+    /// See [`FinalMoveSelection`], the max-robust child isn't extended
+    pub fn set_final_move_selection(&mut self, selection: FinalMoveSelection) {
+        self.tree.set_final_move_selection(selection);
+    }
+
+    /// See [`Mcts::set_observer`]
+    pub fn set_observer(&mut self, observer: Box<dyn SearchObserver<T::Action>>) {
+        self.reporter.set_observer(observer);
+    }
+
+    /// See [`Mcts::set_report_interval`]
+    pub fn set_report_interval(&mut self, interval: Option<Duration>) {
+        self.reporter.set_interval(interval);
+    }
+
+    pub fn search(&mut self, limits: &SearchLimits, root_state: T) -> T::Action {
+        let started = Instant::now();
+        self.tree.set_root(root_state);
+
+        let iterations = self.tree.drive(limits, started, &mut self.reporter);
+        self.reporter
+            .finish(self.tree.search_info(iterations, started.elapsed()));
+
+        self.tree.best_action()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fastrand::Rng;
+
+    use super::OpenLoopMcts;
+    use crate::{
+        driver::SearchDriver,
+        limits::SearchLimits,
+        state::{tests::quiet, State},
+    };
+
+    /// A single decision: action 0 wins with probability 0.3, action 1
+    /// with probability 0.6
+    #[derive(Clone)]
+    struct Gamble {
+        won: Option<bool>,
+    }
+
+    impl State for Gamble {
+        type Action = usize;
+
+        fn possible_actions(&self, rng: &mut Rng) -> Vec<Self::Action> {
+            let mut actions = match self.won {
+                Some(_) => vec![],
+                None => vec![0, 1],
+            };
+            rng.shuffle(&mut actions);
+
+            actions
+        }
+
+        fn apply_action(&self, action: Self::Action) -> Self {
+            self.apply_action_with(action, &mut Rng::new())
+        }
+
+        fn apply_action_with(&self, action: Self::Action, rng: &mut Rng) -> Self {
+            let p = [0.3, 0.6][action];
+            Gamble {
+                won: Some(rng.f32() < p),
+            }
+        }
+
+        fn last_action(&self) -> Option<Self::Action> {
+            None
+        }
+
+        fn reward(&self, _perspective: &Self) -> f32 {
+            if self.won == Some(true) {
+                1.
+            } else {
+                0.
+            }
+        }
+
+        fn is_terminal(&mut self, _depth: usize) -> bool {
+            self.won.is_some()
+        }
+    }
+
+    #[test]
+    fn test_open_loop_averages_outcomes() {
+        let mut searcher = quiet(OpenLoopMcts::default());
+        searcher.set_seed(3);
+
+        let action = searcher.search(&SearchLimits::iterations(2000), Gamble { won: None });
+        let tree = searcher.tree();
+
+        assert_eq!(action, 1);
+        assert_eq!(tree.size(), 3);
+        for &child_id in &tree.nodes[0].child_ids {
+            let child = &tree.nodes[child_id];
+            let p = [0.3, 0.6][child.action.unwrap()];
+            assert!((child.mean_score() - p).abs() < 0.1);
+        }
+    }
+}
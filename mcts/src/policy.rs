@@ -0,0 +1,178 @@
+use fastrand::Rng;
+
+use crate::{node::Node, state::State};
+
+/// Tree policy used to pick which child to descend into,
+/// the child with the highest score gets selected
+pub trait SelectionPolicy<T>: Send + Sync
+where
+    T: State,
+{
+    /// `value` is the tree's estimate of the child's mean score, which is
+    /// its plain mean unless e.g. RAVE blends in other statistics
+    fn score(&self, parent: &Node<T>, child: &Node<T>, value: f32, rng: &mut Rng) -> f32;
+}
+
+/// Get a policy by its name, e.g. from a command line argument
+pub fn from_name<T>(name: &str, c: f32) -> Option<Box<dyn SelectionPolicy<T>>>
+where
+    T: State,
+{
+    let policy: Box<dyn SelectionPolicy<T>> = match name {
+        "ucb1" => Box::new(Ucb1 { c }),
+        "ucb1-tuned" => Box::new(Ucb1Tuned { c }),
+        "puct" => Box::new(Puct { c }),
+        "thompson" => Box::new(ThompsonSampling),
+        _ => return None,
+    };
+
+    Some(policy)
+}
+
+/// `mean + c * sqrt(2 ln N / n)`
+#[derive(Clone, Copy)]
+pub struct Ucb1 {
+    pub c: f32,
+}
+
+impl Default for Ucb1 {
+    fn default() -> Self {
+        Ucb1 { c: 1. }
+    }
+}
+
+impl<T> SelectionPolicy<T> for Ucb1
+where
+    T: State,
+{
+    fn score(&self, parent: &Node<T>, child: &Node<T>, value: f32, _rng: &mut Rng) -> f32 {
+        if !child.is_explored() {
+            return f32::INFINITY;
+        }
+
+        let n = child.num_sims() as f32;
+        value + self.c * (2. * (parent.num_sims() as f32).ln() / n).sqrt()
+    }
+}
+
+/// UCB1 with the exploration term bounded by the
+/// observed variance of the child's rewards
+#[derive(Clone, Copy)]
+pub struct Ucb1Tuned {
+    pub c: f32,
+}
+
+impl Default for Ucb1Tuned {
+    fn default() -> Self {
+        Ucb1Tuned { c: 1. }
+    }
+}
+
+impl<T> SelectionPolicy<T> for Ucb1Tuned
+where
+    T: State,
+{
+    fn score(&self, parent: &Node<T>, child: &Node<T>, value: f32, _rng: &mut Rng) -> f32 {
+        if !child.is_explored() {
+            return f32::INFINITY;
+        }
+
+        let n = child.num_sims() as f32;
+        let log_n = (parent.num_sims() as f32).ln();
+        let variance_bound = child.variance() + (2. * log_n / n).sqrt();
+
+        value + self.c * (log_n / n * variance_bound.min(0.25)).sqrt()
+    }
+}
+
+/// AlphaZero style `mean + c * prior * sqrt(N) / (1 + n)`
+#[derive(Clone, Copy)]
+pub struct Puct {
+    pub c: f32,
+}
+
+impl Default for Puct {
+    fn default() -> Self {
+        Puct { c: 1. }
+    }
+}
+
+impl<T> SelectionPolicy<T> for Puct
+where
+    T: State,
+{
+    fn score(&self, parent: &Node<T>, child: &Node<T>, value: f32, _rng: &mut Rng) -> f32 {
+        let mean = if child.is_explored() { value } else { 0. };
+        let n = child.num_sims() as f32;
+
+        mean + self.c * child.prior * (parent.num_sims() as f32).sqrt() / (1. + n)
+    }
+}
+
+/// Samples each child's value from a beta distribution
+/// fitted on its rewards
+#[derive(Clone, Copy, Default)]
+pub struct ThompsonSampling;
+
+impl<T> SelectionPolicy<T> for ThompsonSampling
+where
+    T: State,
+{
+    fn score(&self, _parent: &Node<T>, child: &Node<T>, value: f32, rng: &mut Rng) -> f32 {
+        let n = child.num_sims() as f32;
+        let wins = if child.is_explored() { value * n } else { 0. };
+        let losses = n - wins;
+
+        sample_beta(rng, 1. + wins, 1. + losses)
+    }
+}
+
+fn sample_beta(rng: &mut Rng, alpha: f32, beta: f32) -> f32 {
+    let x = sample_gamma(rng, alpha);
+    let y = sample_gamma(rng, beta);
+
+    x / (x + y)
+}
+
+/// Marsaglia and Tsang's method, only valid for `shape >= 1`
+fn sample_gamma(rng: &mut Rng, shape: f32) -> f32 {
+    let d = shape - 1. / 3.;
+    let c = 1. / (9. * d).sqrt();
+
+    loop {
+        let x = sample_normal(rng);
+        let v = (1. + c * x).powi(3);
+        if v <= 0. {
+            continue;
+        }
+
+        let u = rng.f32();
+        if u.ln() < 0.5 * x * x + d - d * v + d * v.ln() {
+            return d * v;
+        }
+    }
+}
+
+/// Box-Muller transform
+fn sample_normal(rng: &mut Rng) -> f32 {
+    let u1 = 1. - rng.f32();
+    let u2 = rng.f32();
+
+    (-2. * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use fastrand::Rng;
+
+    use super::sample_beta;
+
+    #[test]
+    fn test_beta_sample_mean() {
+        let mut rng = Rng::with_seed(7);
+        let n = 20_000;
+        let mean = (0..n).map(|_| sample_beta(&mut rng, 3., 7.)).sum::<f32>() / n as f32;
+
+        assert!((mean - 0.3).abs() < 0.01);
+    }
+}
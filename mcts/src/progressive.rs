@@ -0,0 +1,65 @@
+/// Limits the number of children of a node to `ceil(c * n^alpha)`,
+/// where `n` is the node's number of simulations. Actions with the
+/// highest prior are expanded first
+#[derive(Clone, Copy, Debug)]
+pub struct ProgressiveWidening {
+    pub c: f32,
+    pub alpha: f32,
+}
+
+impl Default for ProgressiveWidening {
+    fn default() -> Self {
+        ProgressiveWidening { c: 1., alpha: 0.5 }
+    }
+}
+
+impl ProgressiveWidening {
+    pub fn max_children(&self, num_sims: usize) -> usize {
+        ((self.c * (num_sims as f32).powf(self.alpha)).ceil() as usize).max(1)
+    }
+}
+
+/// Adds `weight * prior / (n + 1)` to the selection score of a child,
+/// guiding the search by the priors while the child has few simulations
+#[derive(Clone, Copy, Debug)]
+pub struct ProgressiveBias {
+    pub weight: f32,
+}
+
+impl Default for ProgressiveBias {
+    fn default() -> Self {
+        ProgressiveBias { weight: 1. }
+    }
+}
+
+impl ProgressiveBias {
+    pub fn bias(&self, prior: f32, num_sims: usize) -> f32 {
+        self.weight * prior / (num_sims as f32 + 1.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProgressiveWidening;
+    use crate::{
+        limits::SearchLimits,
+        state::tests::{quiet_mcts, TicTacToe},
+    };
+
+    #[test]
+    fn test_progressive_widening_expands_centre_first() {
+        let widening = ProgressiveWidening { c: 1., alpha: 0.25 };
+        let mut searcher = quiet_mcts();
+        searcher.set_progressive_widening(Some(widening));
+        searcher.search(&SearchLimits::iterations(2000), TicTacToe::default());
+
+        let tree = searcher.tree();
+        let root = &tree.nodes[searcher.root_id().unwrap()];
+
+        assert_eq!(root.child_actions[0], 4);
+        for corner in [0, 2, 6, 8] {
+            assert!(root.child_actions[1..5].contains(&corner));
+        }
+        assert!(root.child_ids.len() <= widening.max_children(root.num_sims()));
+    }
+}
@@ -0,0 +1,66 @@
+/// How much weight the AMAF value gets compared to the normal mean,
+/// this should go to 0 as the number of simulations grows
+#[derive(Clone, Copy, Debug)]
+pub enum RaveSchedule {
+    /// `beta = sqrt(k / (3n + k))`, the AMAF value and mean
+    /// get equal weight after `k` simulations
+    HandSelected { k: f32 },
+    /// Minimum MSE schedule, `bias` is the assumed difference
+    /// between the AMAF value and the true value
+    MinimumMse { bias: f32 },
+}
+
+impl Default for RaveSchedule {
+    fn default() -> Self {
+        RaveSchedule::HandSelected { k: 1000. }
+    }
+}
+
+impl RaveSchedule {
+    pub fn beta(&self, num_sims: usize, amaf_sims: usize) -> f32 {
+        let n = num_sims as f32;
+        let amaf_n = amaf_sims as f32;
+
+        match *self {
+            RaveSchedule::HandSelected { k } => (k / (3. * n + k)).sqrt(),
+            RaveSchedule::MinimumMse { bias } => {
+                amaf_n / (n + amaf_n + 4. * bias * bias * n * amaf_n)
+            }
+        }
+    }
+
+    /// Blend the mean of a node with its AMAF mean
+    pub fn blend(&self, mean: f32, num_sims: usize, amaf_mean: f32, amaf_sims: usize) -> f32 {
+        if amaf_sims == 0 {
+            return mean;
+        }
+        if num_sims == 0 {
+            return amaf_mean;
+        }
+
+        let beta = self.beta(num_sims, amaf_sims);
+        (1. - beta) * mean + beta * amaf_mean
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RaveSchedule;
+    use crate::{
+        limits::SearchLimits,
+        state::tests::{quiet_mcts, TicTacToe},
+    };
+
+    #[test]
+    fn test_rave_updates_siblings() {
+        let mut searcher = quiet_mcts();
+        searcher.set_rave(Some(RaveSchedule::default()));
+        searcher.search(&SearchLimits::iterations(2000), TicTacToe::default());
+
+        let tree = searcher.tree();
+        for &child_id in &tree.nodes[searcher.root_id().unwrap()].child_ids {
+            let child = &tree.nodes[child_id];
+            assert!(child.amaf_sims() > child.num_sims());
+        }
+    }
+}
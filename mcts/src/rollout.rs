@@ -0,0 +1,535 @@
+use std::collections::HashMap;
+
+use fastrand::Rng;
+
+use crate::state::State;
+
+/// Chooses the actions of simulations, and can learn from the simulations
+/// of the current search. Tables are cleared when a search starts
+pub trait RolloutPolicy<T>: Send
+where
+    T: State,
+{
+    /// The next action of a simulation from `state`. `history` holds the
+    /// actions played since the root, along the tree and then the simulation
+    fn select(&mut self, state: &T, history: &[T::Action], rng: &mut Rng) -> T::Action;
+
+    /// Learn from a finished simulation. The actions from `start` on in
+    /// `history` were played by the simulation, `rewards[i]` is the reward
+    /// of the player who played `history[start + i]`, `None` for chance
+    /// outcomes
+    fn update(&mut self, _history: &[T::Action], _start: usize, _rewards: &[Option<f32>]) {}
+
+    /// Forget everything learned, called when a search starts
+    fn reset(&mut self) {}
+
+    /// An untrained copy, for searches running on several threads
+    fn fork(&self) -> Box<dyn RolloutPolicy<T>>;
+}
+
+/// Get a rollout policy by its name, e.g. from a command line argument
+pub fn from_name<T>(name: &str) -> Option<Box<dyn RolloutPolicy<T>>>
+where
+    T: State + 'static,
+    T::Action: Send,
+{
+    let policy: Box<dyn RolloutPolicy<T>> = match name {
+        "uniform" => Box::new(UniformRollout),
+        "heuristic" => Box::new(EpsilonGreedy::default()),
+        "mast" => Box::new(Mast::default()),
+        "nst" => Box::new(Nst::default()),
+        "lgr" => Box::new(LastGoodReply::default()),
+        _ => return None,
+    };
+
+    Some(policy)
+}
+
+/// Uniformly random actions
+#[derive(Clone, Copy, Default)]
+pub struct UniformRollout;
+
+impl<T> RolloutPolicy<T> for UniformRollout
+where
+    T: State,
+{
+    fn select(&mut self, state: &T, _history: &[T::Action], rng: &mut Rng) -> T::Action {
+        state.possible_actions(rng).pop().unwrap()
+    }
+
+    fn fork(&self) -> Box<dyn RolloutPolicy<T>> {
+        Box::new(*self)
+    }
+}
+
+/// The action with the highest [`State::action_prior`],
+/// or a random one with probability `epsilon`
+#[derive(Clone, Copy)]
+pub struct EpsilonGreedy {
+    pub epsilon: f32,
+}
+
+impl Default for EpsilonGreedy {
+    fn default() -> Self {
+        EpsilonGreedy { epsilon: 0.1 }
+    }
+}
+
+impl<T> RolloutPolicy<T> for EpsilonGreedy
+where
+    T: State,
+{
+    fn select(&mut self, state: &T, _history: &[T::Action], rng: &mut Rng) -> T::Action {
+        let actions = state.possible_actions(rng);
+        epsilon_greedy(actions, self.epsilon, rng, |action| {
+            state.action_prior(action)
+        })
+    }
+
+    fn fork(&self) -> Box<dyn RolloutPolicy<T>> {
+        Box::new(*self)
+    }
+}
+
+/// Random action with probability `epsilon`, otherwise the best by `value`.
+/// Ties keep the shuffled order of the actions
+fn epsilon_greedy<A>(
+    mut actions: Vec<A>,
+    epsilon: f32,
+    rng: &mut Rng,
+    value: impl Fn(&A) -> f32,
+) -> A {
+    if rng.f32() < epsilon {
+        return actions.pop().unwrap();
+    }
+
+    let best = (0..actions.len())
+        .max_by(|&i, &j| value(&actions[i]).total_cmp(&value(&actions[j])))
+        .unwrap();
+    actions.swap_remove(best)
+}
+
+/// Sum of rewards and number of simulations
+type Stats = (f32, usize);
+
+fn mean((score, visits): Stats) -> f32 {
+    score / visits as f32
+}
+
+/// Move-Average Sampling Technique: the action with the best mean
+/// reward over the simulations that played it, wherever that was,
+/// or a random one with probability `epsilon`. Unseen actions get `0.5`
+pub struct Mast<T>
+where
+    T: State,
+{
+    pub epsilon: f32,
+    table: HashMap<T::Action, Stats>,
+}
+
+impl<T> Default for Mast<T>
+where
+    T: State,
+{
+    fn default() -> Self {
+        Mast {
+            epsilon: 0.4,
+            table: HashMap::new(),
+        }
+    }
+}
+
+impl<T> Mast<T>
+where
+    T: State,
+{
+    pub fn value(&self, action: &T::Action) -> f32 {
+        self.table.get(action).map_or(0.5, |&stats| mean(stats))
+    }
+}
+
+impl<T> RolloutPolicy<T> for Mast<T>
+where
+    T: State + 'static,
+    T::Action: Send,
+{
+    fn select(&mut self, state: &T, _history: &[T::Action], rng: &mut Rng) -> T::Action {
+        let actions = state.possible_actions(rng);
+        epsilon_greedy(actions, self.epsilon, rng, |action| self.value(action))
+    }
+
+    fn update(&mut self, history: &[T::Action], start: usize, rewards: &[Option<f32>]) {
+        for (action, reward) in history[start..].iter().zip(rewards) {
+            if let Some(reward) = reward {
+                let stats = self.table.entry(action.clone()).or_default();
+                stats.0 += reward;
+                stats.1 += 1;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.table.clear();
+    }
+
+    fn fork(&self) -> Box<dyn RolloutPolicy<T>> {
+        Box::new(Mast::<T> {
+            epsilon: self.epsilon,
+            ..Default::default()
+        })
+    }
+}
+
+/// N-gram Selection Technique: MAST over sequences of up to `max_length`
+/// consecutive actions ending with the candidate action. The value of an
+/// action averages the means of its n-grams, where longer n-grams only
+/// count after `min_visits` simulations
+pub struct Nst<T>
+where
+    T: State,
+{
+    pub epsilon: f32,
+    pub max_length: usize,
+    pub min_visits: usize,
+    table: HashMap<Vec<T::Action>, Stats>,
+}
+
+impl<T> Default for Nst<T>
+where
+    T: State,
+{
+    fn default() -> Self {
+        Nst {
+            epsilon: 0.1,
+            max_length: 3,
+            min_visits: 7,
+            table: HashMap::new(),
+        }
+    }
+}
+
+impl<T> Nst<T>
+where
+    T: State,
+{
+    /// Value of playing `action` after `history`
+    pub fn value(&self, history: &[T::Action], action: &T::Action) -> f32 {
+        let mut gram = vec![action.clone()];
+        let mut total = 0.;
+        let mut count = 0;
+
+        for length in 1..=self.max_length {
+            if length > 1 {
+                let Some(previous) = history.len().checked_sub(length - 1) else {
+                    break;
+                };
+                gram.insert(0, history[previous].clone());
+            }
+
+            match self.table.get(&gram) {
+                Some(&stats) if length == 1 || stats.1 >= self.min_visits => {
+                    total += mean(stats);
+                    count += 1;
+                }
+                _ => break,
+            }
+        }
+
+        match count {
+            0 => 0.5,
+            _ => total / count as f32,
+        }
+    }
+}
+
+impl<T> RolloutPolicy<T> for Nst<T>
+where
+    T: State + 'static,
+    T::Action: Send,
+{
+    fn select(&mut self, state: &T, history: &[T::Action], rng: &mut Rng) -> T::Action {
+        let actions = state.possible_actions(rng);
+        epsilon_greedy(actions, self.epsilon, rng, |action| {
+            self.value(history, action)
+        })
+    }
+
+    fn update(&mut self, history: &[T::Action], start: usize, rewards: &[Option<f32>]) {
+        for (i, reward) in rewards.iter().enumerate() {
+            let Some(reward) = reward else {
+                continue;
+            };
+
+            let end = start + i + 1;
+            for length in 1..=self.max_length.min(end) {
+                let stats = self
+                    .table
+                    .entry(history[end - length..end].to_vec())
+                    .or_default();
+                stats.0 += reward;
+                stats.1 += 1;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.table.clear();
+    }
+
+    fn fork(&self) -> Box<dyn RolloutPolicy<T>> {
+        Box::new(Nst::<T> {
+            epsilon: self.epsilon,
+            max_length: self.max_length,
+            min_visits: self.min_visits,
+            ..Default::default()
+        })
+    }
+}
+
+/// Last-Good-Reply: remembers the last reply to every action that won a
+/// simulation, and plays it whenever it's legal, otherwise a random action.
+/// With `forgetting` (LGRF), replies are forgotten again once they lose
+pub struct LastGoodReply<T>
+where
+    T: State,
+{
+    pub forgetting: bool,
+    replies: HashMap<T::Action, T::Action>,
+}
+
+impl<T> Default for LastGoodReply<T>
+where
+    T: State,
+{
+    fn default() -> Self {
+        LastGoodReply {
+            forgetting: true,
+            replies: HashMap::new(),
+        }
+    }
+}
+
+impl<T> LastGoodReply<T>
+where
+    T: State,
+{
+    pub fn reply(&self, action: &T::Action) -> Option<&T::Action> {
+        self.replies.get(action)
+    }
+}
+
+impl<T> RolloutPolicy<T> for LastGoodReply<T>
+where
+    T: State + 'static,
+    T::Action: Send,
+{
+    fn select(&mut self, state: &T, history: &[T::Action], rng: &mut Rng) -> T::Action {
+        let mut actions = state.possible_actions(rng);
+        let reply = history.last().and_then(|last| self.replies.get(last));
+
+        match reply.and_then(|reply| actions.iter().position(|action| action == reply)) {
+            Some(i) => actions.swap_remove(i),
+            None => actions.pop().unwrap(),
+        }
+    }
+
+    fn update(&mut self, history: &[T::Action], start: usize, rewards: &[Option<f32>]) {
+        for (i, reward) in rewards.iter().enumerate() {
+            let j = start + i;
+            let (Some(reward), Some(previous)) = (reward, j.checked_sub(1)) else {
+                continue;
+            };
+
+            let (previous, reply) = (&history[previous], &history[j]);
+            if *reward > 0.5 {
+                self.replies.insert(previous.clone(), reply.clone());
+            } else if *reward < 0.5 && self.forgetting && self.replies.get(previous) == Some(reply)
+            {
+                self.replies.remove(previous);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.replies.clear();
+    }
+
+    fn fork(&self) -> Box<dyn RolloutPolicy<T>> {
+        Box::new(LastGoodReply::<T> {
+            forgetting: self.forgetting,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use fastrand::Rng;
+
+    use super::{from_name, LastGoodReply, Mast, Nst, RolloutPolicy};
+    use crate::{
+        limits::SearchLimits,
+        state::{
+            tests::{quiet_mcts, TicTacToe},
+            State,
+        },
+    };
+
+    /// Counts up by 1 or 2, never ends
+    #[derive(Clone)]
+    struct Counter(usize);
+
+    impl State for Counter {
+        type Action = usize;
+
+        fn possible_actions(&self, rng: &mut Rng) -> Vec<Self::Action> {
+            let mut actions = vec![1, 2];
+            rng.shuffle(&mut actions);
+
+            actions
+        }
+
+        fn apply_action(&self, action: Self::Action) -> Self {
+            Counter(self.0 + action)
+        }
+
+        fn last_action(&self) -> Option<Self::Action> {
+            None
+        }
+
+        fn reward(&self, _perspective: &Self) -> f32 {
+            0.5
+        }
+
+        fn is_terminal(&mut self, _depth: usize) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_policies_learn_from_simulations() {
+        let mut rng = Rng::with_seed(0);
+        // the player playing 2 after a 1 won, the other one lost
+        let history = [1, 1, 2];
+        let rewards = [None, Some(0.), Some(1.)];
+
+        let mut mast = Mast::<Counter> {
+            epsilon: 0.,
+            ..Default::default()
+        };
+        mast.update(&history, 0, &rewards);
+        assert_eq!(mast.value(&1), 0.);
+        assert_eq!(mast.value(&2), 1.);
+        assert_eq!(mast.select(&Counter(0), &[], &mut rng), 2);
+
+        let mut nst = Nst::<Counter> {
+            min_visits: 1,
+            ..Default::default()
+        };
+        nst.update(&history, 0, &rewards);
+        assert_eq!(nst.value(&[1], &2), 1.);
+        assert_eq!(nst.value(&[1, 1], &1), 0.);
+
+        let mut lgr = LastGoodReply::<Counter>::default();
+        lgr.update(&history, 0, &rewards);
+        assert_eq!(lgr.reply(&1), Some(&2));
+        for _ in 0..10 {
+            assert_eq!(lgr.select(&Counter(0), &[1], &mut rng), 2);
+        }
+
+        // the reply is forgotten once it loses
+        lgr.update(&[1, 2], 1, &[Some(0.)]);
+        assert_eq!(lgr.reply(&1), None);
+
+        mast.reset();
+        assert_eq!(mast.value(&2), 0.5);
+    }
+
+    /// Hands the search a policy the test can look into afterwards
+    struct Shared<P>(Arc<Mutex<P>>);
+
+    impl<T, P> RolloutPolicy<T> for Shared<P>
+    where
+        T: State,
+        P: RolloutPolicy<T>,
+    {
+        fn select(&mut self, state: &T, history: &[T::Action], rng: &mut Rng) -> T::Action {
+            self.0.lock().unwrap().select(state, history, rng)
+        }
+
+        fn update(&mut self, history: &[T::Action], start: usize, rewards: &[Option<f32>]) {
+            self.0.lock().unwrap().update(history, start, rewards);
+        }
+
+        fn reset(&mut self) {
+            self.0.lock().unwrap().reset();
+        }
+
+        fn fork(&self) -> Box<dyn RolloutPolicy<T>> {
+            self.0.lock().unwrap().fork()
+        }
+    }
+
+    /// Search the empty board using `policy`, without the solver
+    /// ending the search early, and check the full budget was spent
+    fn search_with<P>(policy: &Arc<Mutex<P>>)
+    where
+        P: RolloutPolicy<TicTacToe> + 'static,
+    {
+        let mut searcher = quiet_mcts();
+        searcher.set_seed(0);
+        searcher.set_solver(false);
+        searcher.set_rollout_policy(Box::new(Shared(policy.clone())));
+
+        searcher.search(&SearchLimits::iterations(1000), TicTacToe::default());
+        assert_eq!(searcher.tree().nodes[0].num_sims(), 1000);
+    }
+
+    #[test]
+    fn test_rollout_policies_find_win() {
+        for name in ["uniform", "heuristic", "mast", "nst", "lgr"] {
+            let mut searcher = quiet_mcts();
+            searcher.set_seed(0);
+            searcher.set_solver(false);
+            searcher.set_rollout_policy(from_name(name).unwrap());
+
+            let limits = SearchLimits::iterations(1000);
+            assert_eq!(
+                searcher.search(&limits, TicTacToe::win_in_one()),
+                2,
+                "{name}"
+            );
+            assert_eq!(searcher.tree().nodes[0].num_sims(), 1000, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_rollout_policies_learn_while_searching() {
+        let mast = Arc::new(Mutex::new(Mast::default()));
+        search_with(&mast);
+        let mast = mast.lock().unwrap();
+        let visits = mast.table.values().map(|(_, visits)| visits).sum::<usize>();
+        assert!(visits > 1000);
+        assert!((0..9).any(|cell| mast.value(&cell) != 0.5));
+
+        let nst = Arc::new(Mutex::new(Nst::default()));
+        search_with(&nst);
+        let nst = nst.lock().unwrap();
+        assert!(nst.table.keys().any(|gram| gram.len() == nst.max_length));
+        assert!(nst
+            .table
+            .values()
+            .any(|&(_, visits)| visits >= nst.min_visits));
+
+        let lgr = Arc::new(Mutex::new(LastGoodReply::default()));
+        search_with(&lgr);
+        let lgr = lgr.lock().unwrap();
+        assert!(!lgr.replies.is_empty());
+        assert!(lgr
+            .replies
+            .iter()
+            .all(|(previous, reply)| previous != reply && *reply < 9));
+    }
+}
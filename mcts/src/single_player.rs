@@ -0,0 +1,459 @@
+use std::{
+    fmt::Display,
+    hash::{DefaultHasher, Hash, Hasher},
+    time::{Duration, Instant},
+};
+
+use fastrand::Rng;
+
+use crate::{
+    driver::{ActionTree, Reporter, SearchDriver},
+    limits::SearchLimits,
+    observer::{SearchInfo, SearchObserver},
+    state::State,
+};
+
+/// Puzzles and optimization problems, where a single player looks for the
+/// action sequence with the highest score. Actions have to be
+/// deterministic, [`State::reward`] and chance events are never used
+pub trait SinglePlayer: State + Sized {
+    /// Score of a terminal state, higher is better. Scores can have
+    /// any range, the search normalizes them by the ones it has seen
+    fn score(&self) -> f32;
+
+    /// Code of an action played in this state, [`Nrpa`](crate::nested::Nrpa)
+    /// learns a weight per code. By default the action alone, whatever the state
+    fn action_code(&self, action: &Self::Action) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        action.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Node of an [`SpTree`]
+pub struct SpNode<A> {
+    /// `None` for the root
+    pub action: Option<A>,
+    pub child_ids: Vec<usize>,
+    /// Actions without a child yet
+    untried: Vec<A>,
+    num_sims: usize,
+    score: f32,
+    /// Sum of the squared scores, for the variance
+    score_sq: f32,
+    /// Best score of any simulation through this node
+    best_score: f32,
+}
+
+impl<A> SpNode<A> {
+    fn new(action: Option<A>, untried: Vec<A>) -> Self {
+        SpNode {
+            action,
+            child_ids: vec![],
+            untried,
+            num_sims: 0,
+            score: 0.,
+            score_sq: 0.,
+            best_score: f32::NEG_INFINITY,
+        }
+    }
+
+    pub fn num_sims(&self) -> usize {
+        self.num_sims
+    }
+
+    pub fn mean_score(&self) -> f32 {
+        self.score / self.num_sims as f32
+    }
+
+    pub fn variance(&self) -> f32 {
+        let mean = self.mean_score();
+        (self.score_sq / self.num_sims as f32 - mean * mean).max(0.)
+    }
+
+    pub fn best_score(&self) -> f32 {
+        self.best_score
+    }
+}
+
+/// Single-player MCTS tree. Nodes don't store states, every iteration
+/// replays the actions from the root. Selection is UCB1 on the mean score,
+/// normalized by the lowest and highest score seen so far, plus a term
+/// favouring children with a high variance
+pub struct SpTree<T>
+where
+    T: SinglePlayer,
+{
+    pub nodes: Vec<SpNode<T::Action>>,
+    root_state: Option<T>,
+    /// Exploration constant of UCB1
+    pub c: f32,
+    /// Added to the variance of every child, keeps rarely
+    /// visited children with a low variance promising
+    pub d: f32,
+    min_score: f32,
+    max_score: f32,
+    /// Highest scoring sequence of actions from the root found so far
+    best: Option<(f32, Vec<T::Action>)>,
+    rng: Rng,
+}
+
+impl<T> Default for SpTree<T>
+where
+    T: SinglePlayer,
+{
+    fn default() -> Self {
+        SpTree {
+            nodes: vec![],
+            root_state: None,
+            c: 0.5,
+            d: 0.1,
+            min_score: f32::INFINITY,
+            max_score: f32::NEG_INFINITY,
+            best: None,
+            rng: Rng::new(),
+        }
+    }
+}
+
+impl<T> SpTree<T>
+where
+    T: SinglePlayer + Clone,
+{
+    /// Throw away the tree and start over from `state`
+    pub fn set_root(&mut self, state: T) {
+        let untried = state.possible_actions(&mut self.rng);
+
+        self.nodes.clear();
+        self.nodes.push(SpNode::new(None, untried));
+        self.root_state = Some(state);
+        self.min_score = f32::INFINITY;
+        self.max_score = f32::NEG_INFINITY;
+        self.best = None;
+    }
+
+    /// See [`Tree::set_seed`](crate::tree::Tree::set_seed)
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::with_seed(seed);
+    }
+
+    /// Highest scoring complete action sequence found so far, with its score
+    pub fn best_sequence(&self) -> Option<(f32, &[T::Action])> {
+        self.best
+            .as_ref()
+            .map(|(score, actions)| (*score, actions.as_slice()))
+    }
+
+    /// None for terminal nodes
+    fn select_child(&self, node_id: usize) -> Option<usize> {
+        let range = self.max_score - self.min_score;
+        let normalize = |x: f32| {
+            if range > 0. {
+                (x - self.min_score) / range
+            } else {
+                0.
+            }
+        };
+        let log_n = (self.nodes[node_id].num_sims as f32).ln();
+
+        self.nodes[node_id]
+            .child_ids
+            .iter()
+            .copied()
+            .max_by(|&a, &b| {
+                let value = |child: &SpNode<T::Action>| {
+                    let n = child.num_sims as f32;
+                    let variance = if range > 0. {
+                        child.variance() / (range * range)
+                    } else {
+                        0.
+                    };
+
+                    normalize(child.mean_score())
+                        + self.c * (2. * log_n / n).sqrt()
+                        + (variance + self.d / n).sqrt()
+                };
+
+                value(&self.nodes[a]).total_cmp(&value(&self.nodes[b]))
+            })
+    }
+
+    fn backpropagate(&mut self, path: &[usize], score: f32) {
+        for &id in path {
+            let node = &mut self.nodes[id];
+            node.num_sims += 1;
+            node.score += score;
+            node.score_sq += score * score;
+            node.best_score = node.best_score.max(score);
+        }
+    }
+}
+
+impl<T> ActionTree for SpTree<T>
+where
+    T: SinglePlayer,
+{
+    type Action = T::Action;
+
+    fn children(&self, node_id: usize) -> impl Iterator<Item = usize> + '_ {
+        self.nodes[node_id].child_ids.iter().copied()
+    }
+
+    fn action(&self, node_id: usize) -> &T::Action {
+        self.nodes[node_id].action.as_ref().unwrap()
+    }
+
+    fn num_sims(&self, node_id: usize) -> usize {
+        self.nodes[node_id].num_sims()
+    }
+
+    fn mean_score(&self, node_id: usize) -> f32 {
+        self.nodes[node_id].mean_score()
+    }
+
+    fn std_dev(&self, node_id: usize) -> Option<f32> {
+        Some(self.nodes[node_id].variance().sqrt())
+    }
+}
+
+impl<T> SearchDriver for SpTree<T>
+where
+    T: SinglePlayer + Clone,
+    T::Action: Display,
+{
+    type Action = T::Action;
+
+    /// Select a path, expand one untried action, play random actions until
+    /// the end and backpropagate the score
+    fn iterate(&mut self) {
+        let mut state = self.root_state.clone().unwrap();
+        let mut path = vec![0];
+        let mut actions = vec![];
+        let mut node_id = 0;
+
+        loop {
+            if let Some(action) = self.nodes[node_id].untried.pop() {
+                state = state.apply_action(action.clone());
+                let untried = state.possible_actions(&mut self.rng);
+
+                let child_id = self.nodes.len();
+                self.nodes.push(SpNode::new(Some(action.clone()), untried));
+                self.nodes[node_id].child_ids.push(child_id);
+
+                path.push(child_id);
+                actions.push(action);
+                break;
+            }
+
+            let Some(child_id) = self.select_child(node_id) else {
+                break;
+            };
+
+            let action = self.nodes[child_id].action.clone().unwrap();
+            state = state.apply_action(action.clone());
+            path.push(child_id);
+            actions.push(action);
+            node_id = child_id;
+        }
+
+        let mut depth = actions.len();
+        while !state.is_terminal(depth) {
+            let action = state.possible_actions(&mut self.rng).pop().unwrap();
+            state = state.apply_action(action.clone());
+            actions.push(action);
+            depth += 1;
+        }
+
+        let score = state.score();
+        self.min_score = self.min_score.min(score);
+        self.max_score = self.max_score.max(score);
+        if self.best.as_ref().is_none_or(|(best, _)| score > *best) {
+            self.best = Some((score, actions));
+        }
+
+        self.backpropagate(&path, score);
+    }
+
+    fn size(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Estimate of the memory used by the nodes in bytes
+    fn memory_usage(&self) -> usize {
+        self.nodes
+            .iter()
+            .map(|node| {
+                std::mem::size_of::<SpNode<T::Action>>()
+                    + node.child_ids.capacity() * std::mem::size_of::<usize>()
+                    + node.untried.capacity() * std::mem::size_of::<T::Action>()
+            })
+            .sum()
+    }
+
+    /// Snapshot of the search, not marked as finished. The value is the
+    /// best score found and the principal variation the best sequence
+    fn search_info(&self, iterations: usize, elapsed: Duration) -> SearchInfo<T::Action> {
+        let pv = self
+            .best
+            .as_ref()
+            .map_or(vec![], |(_, actions)| actions.clone());
+
+        SearchInfo {
+            iterations,
+            elapsed,
+            nodes: self.size(),
+            memory: self.memory_usage(),
+            depth: pv.len(),
+            best_action: pv.first().cloned(),
+            value: self.best.as_ref().map(|(score, _)| *score),
+            pv,
+            ..self.root_info(None)
+        }
+    }
+}
+
+/// Searcher using an [`SpTree`], every search starts from scratch
+pub struct SpMcts<T>
+where
+    T: SinglePlayer,
+{
+    tree: SpTree<T>,
+    reporter: Reporter<T::Action>,
+}
+
+impl<T> Default for SpMcts<T>
+where
+    T: SinglePlayer,
+{
+    fn default() -> Self {
+        SpMcts {
+            tree: SpTree::default(),
+            reporter: Reporter::default(),
+        }
+    }
+}
+
+impl<T> SpMcts<T>
+where
+    T: SinglePlayer + Clone,
+    T::Action: Display,
+{
+    pub fn tree(&self) -> &SpTree<T> {
+        &self.tree
+    }
+
+    pub fn tree_mut(&mut self) -> &mut SpTree<T> {
+        &mut self.tree
+    }
+
+    /// See [`Mcts::set_seed`](crate::Mcts::set_seed)
+    pub fn set_seed(&mut self, seed: u64) {
+        self.tree.set_seed(seed);
+    }
+
+    /// See [`Mcts::set_observer`](crate::Mcts::set_observer)
+    pub fn set_observer(&mut self, observer: Box<dyn SearchObserver<T::Action>>) {
+        self.reporter.set_observer(observer);
+    }
+
+    /// See [`Mcts::set_report_interval`](crate::Mcts::set_report_interval)
+    pub fn set_report_interval(&mut self, interval: Option<Duration>) {
+        self.reporter.set_interval(interval);
+    }
+
+    /// Returns the best complete action sequence found and its score
+    pub fn search(&mut self, limits: &SearchLimits, root_state: T) -> (f32, Vec<T::Action>) {
+        let started = Instant::now();
+        self.tree.set_root(root_state);
+
+        let mut iterations = self.tree.drive(limits, started, &mut self.reporter);
+        // at least one iteration, so there is a sequence to return
+        if iterations == 0 {
+            self.tree.iterate();
+            iterations += 1;
+        }
+        self.reporter
+            .finish(self.tree.search_info(iterations, started.elapsed()));
+
+        let (score, actions) = self.tree.best_sequence().unwrap();
+        (score, actions.to_vec())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use fastrand::Rng;
+
+    use super::{SinglePlayer, SpMcts};
+    use crate::{
+        limits::SearchLimits,
+        state::{tests::quiet, State},
+    };
+
+    /// Pick 6 bits, scoring the sum of the weights of the ones
+    #[derive(Clone)]
+    pub(crate) struct Bits(pub Vec<bool>);
+
+    impl State for Bits {
+        type Action = bool;
+
+        fn possible_actions(&self, rng: &mut Rng) -> Vec<Self::Action> {
+            let mut actions = match self.0.len() {
+                6 => vec![],
+                _ => vec![false, true],
+            };
+            rng.shuffle(&mut actions);
+
+            actions
+        }
+
+        fn apply_action(&self, action: Self::Action) -> Self {
+            let mut bits = self.0.clone();
+            bits.push(action);
+            Bits(bits)
+        }
+
+        fn last_action(&self) -> Option<Self::Action> {
+            self.0.last().copied()
+        }
+
+        fn reward(&self, _perspective: &Self) -> f32 {
+            unreachable!()
+        }
+
+        fn is_terminal(&mut self, _depth: usize) -> bool {
+            self.0.len() == 6
+        }
+    }
+
+    impl SinglePlayer for Bits {
+        fn score(&self) -> f32 {
+            const WEIGHTS: [f32; 6] = [3., -1., 4., -1., -5., 9.];
+
+            self.0
+                .iter()
+                .zip(WEIGHTS)
+                .filter_map(|(&bit, weight)| bit.then_some(weight))
+                .sum()
+        }
+
+        fn action_code(&self, action: &Self::Action) -> u64 {
+            (self.0.len() * 2 + *action as usize) as u64
+        }
+    }
+
+    #[test]
+    fn test_sp_mcts_finds_best_sequence() {
+        let mut searcher = quiet(SpMcts::default());
+        searcher.set_seed(3);
+
+        let (score, actions) = searcher.search(&SearchLimits::iterations(500), Bits(vec![]));
+
+        assert_eq!(score, 16.);
+        assert_eq!(actions, vec![true, false, true, false, false, true]);
+
+        let tree = searcher.tree();
+        assert_eq!(tree.nodes[0].best_score(), 16.);
+        assert!(tree.nodes[0].mean_score() < 16.);
+    }
+}
@@ -0,0 +1,369 @@
+use std::hash::Hash;
+
+use fastrand::Rng;
+
+/// State's should contain all game logic/information
+pub trait State {
+    /// Actions have to be comparable, so the same action
+    /// can be recognised in different parts of the tree
+    type Action: Clone + Eq + Hash;
+
+    /// List of possible actions from this state, shuffled with `rng`
+    /// so searches can be reproduced from their seed
+    fn possible_actions(&self, rng: &mut Rng) -> Vec<Self::Action>;
+
+    fn apply_action(&self, action: Self::Action) -> Self;
+
+    /// For games where the outcome of an action is random, apply it
+    /// sampling the outcome with `rng`. Only open-loop searches and
+    /// simulations use this, see [`OpenLoopTree`](crate::open_loop::OpenLoopTree)
+    fn apply_action_with(&self, action: Self::Action, _rng: &mut Rng) -> Self
+    where
+        Self: Sized,
+    {
+        self.apply_action(action)
+    }
+
+    fn last_action(&self) -> Option<Self::Action>;
+
+    /// Get the reward for reaching this state,
+    /// from the perspective of another state
+    fn reward(&self, perspective: &Self) -> f32;
+
+    /// The player to move, for games with more than two players or where
+    /// players don't strictly take turns. When implemented, simulations use
+    /// [`State::rewards`] and every node is scored for the player who moved
+    /// into it. Has to be implemented for every state, including terminal
+    /// ones. By default two players alternate every ply
+    fn current_player(&self) -> Option<usize> {
+        None
+    }
+
+    /// The reward of every player for reaching this state,
+    /// indexed by player, see [`State::current_player`]. By default for
+    /// two players: [`State::reward`] from this state's perspective goes to
+    /// the player not to move. States with more players have to implement it
+    fn rewards(&self) -> Vec<f32> {
+        let reward = self.reward(self);
+        match self.current_player() {
+            Some(0) => vec![1. - reward, reward],
+            Some(1) => vec![reward, 1. - reward],
+            player => panic!("default rewards are for two players, got player {player:?}"),
+        }
+    }
+
+    fn is_terminal(&mut self, depth: usize) -> bool;
+
+    /// The outcomes of a chance event with their probabilities, when this
+    /// state waits on one, e.g. a dice roll. Outcomes are applied like
+    /// actions, but the search samples them instead of choosing. For
+    /// rewards, the player who moved into an outcome is the player who
+    /// moved into the chance state.
+    ///
+    /// RAVE assumes the players alternate every ply, so it shouldn't be
+    /// combined with chance events
+    fn chance_outcomes(&self) -> Option<Vec<(Self::Action, f32)>> {
+        None
+    }
+
+    /// Hash identifying this state, used to detect transpositions.
+    /// States without a hash are never merged
+    fn hash_key(&self) -> Option<u64> {
+        None
+    }
+
+    /// Heuristic weight of an action, higher is better. Nodes expand their
+    /// most promising actions first and normalize these weights into
+    /// priors for PUCT and progressive bias
+    fn action_prior(&self, _action: &Self::Action) -> f32 {
+        1.
+    }
+}
+
+/// Result of a simulation
+#[derive(Clone, Debug, PartialEq)]
+pub enum Reward {
+    /// From the perspective of the player who moved into the simulated
+    /// state, with two players taking turns
+    TwoPlayer(f32),
+    /// The reward of every player, see [`State::current_player`]
+    Players(Vec<f32>),
+}
+
+impl Reward {
+    /// For searches that only support two players taking turns, which
+    /// reject states implementing [`State::current_player`] up front
+    pub fn two_player(self) -> f32 {
+        match self {
+            Reward::TwoPlayer(reward) => reward,
+            Reward::Players(_) => unreachable!("states with a current player are rejected"),
+        }
+    }
+}
+
+/// Index drawn with a probability proportional to its weight
+pub(crate) fn sample_weighted(weights: impl IntoIterator<Item = f32>, rng: &mut Rng) -> usize {
+    let weights = weights.into_iter().collect::<Vec<_>>();
+    let mut x = rng.f32() * weights.iter().sum::<f32>();
+
+    for (i, weight) in weights.iter().enumerate() {
+        if x < *weight {
+            return i;
+        }
+        x -= weight;
+    }
+
+    weights.len() - 1
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::fmt::Display;
+
+    use fastrand::Rng;
+
+    use super::State;
+    use crate::{
+        compact::CompactMcts,
+        ismcts::{Determinize, Ismcts},
+        limits::SearchLimits,
+        node::Node,
+        observer::{SearchInfo, SearchObserver},
+        open_loop::OpenLoopMcts,
+        policy::SelectionPolicy,
+        single_player::{SinglePlayer, SpMcts},
+        Mcts,
+    };
+
+    const LINES: [[usize; 3]; 8] = [
+        [0, 1, 2],
+        [3, 4, 5],
+        [6, 7, 8],
+        [0, 3, 6],
+        [1, 4, 7],
+        [2, 5, 8],
+        [0, 4, 8],
+        [2, 4, 6],
+    ];
+
+    /// Cells are numbered row by row from the top left, 'x' moves first
+    #[derive(Clone, Copy, Default)]
+    pub(crate) struct TicTacToe {
+        /// A bit per cell for each player
+        cells: [u16; 2],
+        turn: usize,
+        last: Option<usize>,
+    }
+
+    impl TicTacToe {
+        pub(crate) fn play(moves: &[usize]) -> Self {
+            moves
+                .iter()
+                .fold(TicTacToe::default(), |game, &cell| game.apply_action(cell))
+        }
+
+        /// 'x' to move, and wins by playing 2. 'o' threatens 5
+        pub(crate) fn win_in_one() -> Self {
+            TicTacToe::play(&[0, 3, 1, 4])
+        }
+
+        fn winner(&self) -> Option<usize> {
+            (0..2).find(|&player| {
+                LINES
+                    .iter()
+                    .any(|line| line.iter().all(|cell| self.cells[player] & 1 << cell != 0))
+            })
+        }
+
+        fn is_full(&self) -> bool {
+            (self.cells[0] | self.cells[1]) == (1 << 9) - 1
+        }
+    }
+
+    impl State for TicTacToe {
+        type Action = usize;
+
+        fn possible_actions(&self, rng: &mut Rng) -> Vec<Self::Action> {
+            if self.winner().is_some() {
+                return vec![];
+            }
+
+            let taken = self.cells[0] | self.cells[1];
+            let mut actions = (0..9)
+                .filter(|cell| taken & 1 << cell == 0)
+                .collect::<Vec<_>>();
+            rng.shuffle(&mut actions);
+
+            actions
+        }
+
+        fn apply_action(&self, action: Self::Action) -> Self {
+            let mut game = *self;
+            game.cells[self.turn] |= 1 << action;
+            game.turn = 1 - self.turn;
+            game.last = Some(action);
+
+            game
+        }
+
+        fn last_action(&self) -> Option<Self::Action> {
+            self.last
+        }
+
+        fn reward(&self, perspective: &Self) -> f32 {
+            match self.winner() {
+                Some(winner) if winner == 1 - perspective.turn => 1.,
+                Some(_) => 0.,
+                None => 0.5,
+            }
+        }
+
+        fn is_terminal(&mut self, _depth: usize) -> bool {
+            self.winner().is_some() || self.is_full()
+        }
+
+        /// The centre first, then the corners
+        fn action_prior(&self, action: &Self::Action) -> f32 {
+            match action {
+                4 => 3.,
+                0 | 2 | 6 | 8 => 2.,
+                _ => 1.,
+            }
+        }
+
+        fn hash_key(&self) -> Option<u64> {
+            Some((self.cells[0] as u64) << 9 | self.cells[1] as u64)
+        }
+    }
+
+    /// [`TicTacToe`] telling whose turn it is, scored
+    /// with the default [`State::rewards`]
+    #[derive(Clone, Copy)]
+    pub(crate) struct Turns(pub(crate) TicTacToe);
+
+    impl State for Turns {
+        type Action = usize;
+
+        fn possible_actions(&self, rng: &mut Rng) -> Vec<Self::Action> {
+            self.0.possible_actions(rng)
+        }
+
+        fn apply_action(&self, action: Self::Action) -> Self {
+            Turns(self.0.apply_action(action))
+        }
+
+        fn last_action(&self) -> Option<Self::Action> {
+            self.0.last_action()
+        }
+
+        fn reward(&self, perspective: &Self) -> f32 {
+            self.0.reward(&perspective.0)
+        }
+
+        fn current_player(&self) -> Option<usize> {
+            Some(self.0.turn)
+        }
+
+        fn is_terminal(&mut self, depth: usize) -> bool {
+            self.0.is_terminal(depth)
+        }
+    }
+
+    /// Descends into the worst child, so the max and robust child never agree
+    pub(crate) struct Worst;
+
+    impl SelectionPolicy<TicTacToe> for Worst {
+        fn score(
+            &self,
+            _: &Node<TicTacToe>,
+            child: &Node<TicTacToe>,
+            value: f32,
+            _: &mut Rng,
+        ) -> f32 {
+            match child.is_explored() {
+                true => -value,
+                false => f32::INFINITY,
+            }
+        }
+    }
+
+    /// Searchers handing their reports to a [`SearchObserver`]
+    pub(crate) trait Observed<A> {
+        fn set_observer(&mut self, observer: Box<dyn SearchObserver<A>>);
+    }
+
+    impl<T: State + Clone> Observed<T::Action> for Mcts<T> {
+        fn set_observer(&mut self, observer: Box<dyn SearchObserver<T::Action>>) {
+            Mcts::set_observer(self, observer);
+        }
+    }
+
+    impl<T: State + Clone> Observed<T::Action> for CompactMcts<T>
+    where
+        T::Action: Display,
+    {
+        fn set_observer(&mut self, observer: Box<dyn SearchObserver<T::Action>>) {
+            CompactMcts::set_observer(self, observer);
+        }
+    }
+
+    impl<T: State + Clone> Observed<T::Action> for OpenLoopMcts<T>
+    where
+        T::Action: Display,
+    {
+        fn set_observer(&mut self, observer: Box<dyn SearchObserver<T::Action>>) {
+            OpenLoopMcts::set_observer(self, observer);
+        }
+    }
+
+    impl<T: Determinize + Clone> Observed<T::Action> for Ismcts<T>
+    where
+        T::Action: Display,
+    {
+        fn set_observer(&mut self, observer: Box<dyn SearchObserver<T::Action>>) {
+            Ismcts::set_observer(self, observer);
+        }
+    }
+
+    impl<T: SinglePlayer + Clone> Observed<T::Action> for SpMcts<T>
+    where
+        T::Action: Display,
+    {
+        fn set_observer(&mut self, observer: Box<dyn SearchObserver<T::Action>>) {
+            SpMcts::set_observer(self, observer);
+        }
+    }
+
+    /// `searcher` without printing its reports
+    pub(crate) fn quiet<S, A>(mut searcher: S) -> S
+    where
+        S: Observed<A>,
+        A: 'static,
+    {
+        searcher.set_observer(Box::new(|_: &SearchInfo<A>| {}));
+
+        searcher
+    }
+
+    /// A [`Mcts`] that doesn't print its reports
+    pub(crate) fn quiet_mcts<T>() -> Mcts<T>
+    where
+        T: State + Clone,
+        T::Action: 'static,
+    {
+        quiet(Mcts::default())
+    }
+
+    #[test]
+    fn test_default_rewards() {
+        let won = Turns(TicTacToe::play(&[0, 3, 1, 4, 2]));
+        let drawn = Turns(TicTacToe::play(&[0, 1, 2, 4, 3, 5, 7, 6, 8]));
+
+        assert_eq!(won.rewards(), vec![1., 0.]);
+        assert_eq!(drawn.rewards(), vec![0.5, 0.5]);
+
+        let mut searcher = quiet_mcts();
+        let limits = SearchLimits::iterations(2000);
+        assert_eq!(searcher.search(&limits, Turns(TicTacToe::win_in_one())), 2);
+    }
+}
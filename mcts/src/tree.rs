@@ -0,0 +1,1240 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::bound::{OnFull, TreeBound};
+use crate::final_move::{FinalMoveSelection, SelectedBy};
+use crate::hybrid::{solve_moves, HybridMinimax};
+use crate::minimax::ImplicitMinimax;
+use crate::node::{Node, Proof};
+use crate::observer::{ChildInfo, SearchInfo};
+use crate::policy::{SelectionPolicy, Ucb1};
+use crate::progressive::{ProgressiveBias, ProgressiveWidening};
+use crate::rave::RaveSchedule;
+use crate::state::{sample_weighted, Reward, State};
+use fastrand::Rng;
+
+pub struct Tree<T>
+where
+    T: State,
+{
+    /// Slots in `free_ids` are unused, and get recycled for new nodes
+    pub nodes: Vec<Node<T>>,
+    free_ids: Vec<usize>,
+    /// See [`Tree::memory_usage`]
+    memory: usize,
+    bound: Option<TreeBound>,
+
+    /// Maps state hashes to node ids, turning the tree into a
+    /// directed acyclic graph when enabled
+    transpositions: Option<HashMap<u64, usize>>,
+    /// Propagate proven wins and losses up the tree (MCTS-Solver)
+    solver: bool,
+    /// Blend All-Moves-As-First statistics into the value estimates
+    rave: Option<RaveSchedule>,
+    widening: Option<ProgressiveWidening>,
+    bias: Option<ProgressiveBias>,
+    minimax: Option<ImplicitMinimax<T>>,
+    hybrid: HybridMinimax,
+    final_move: FinalMoveSelection,
+    policy: Arc<dyn SelectionPolicy<T>>,
+    rng: Rng,
+}
+
+impl<T> Default for Tree<T>
+where
+    T: State,
+{
+    fn default() -> Self {
+        Tree {
+            nodes: vec![],
+            free_ids: vec![],
+            memory: 0,
+            bound: None,
+            transpositions: None,
+            solver: true,
+            rave: None,
+            widening: None,
+            bias: None,
+            minimax: None,
+            hybrid: HybridMinimax::default(),
+            final_move: FinalMoveSelection::default(),
+            policy: Arc::new(Ucb1::default()),
+            rng: Rng::new(),
+        }
+    }
+}
+
+impl<T> Tree<T>
+where
+    T: State,
+{
+    pub fn set_policy(&mut self, policy: Box<dyn SelectionPolicy<T>>) {
+        self.policy = Arc::from(policy);
+    }
+
+    /// Seed for all randomness of the search: shuffling actions, selection
+    /// policies and, through [`Tree::fork_rng`], simulations
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::with_seed(seed);
+    }
+
+    /// A new generator seeded from this tree's generator
+    pub fn fork_rng(&mut self) -> Rng {
+        Rng::with_seed(self.rng.u64(..))
+    }
+
+    /// An empty tree sharing this tree's configuration, with its own seed
+    pub fn new_like(&self, seed: u64) -> Self {
+        Tree {
+            nodes: vec![],
+            free_ids: vec![],
+            memory: 0,
+            bound: self.bound,
+            transpositions: self.transpositions.as_ref().map(|_| HashMap::new()),
+            solver: self.solver,
+            rave: self.rave,
+            widening: self.widening,
+            bias: self.bias,
+            minimax: self.minimax.clone(),
+            hybrid: self.hybrid,
+            final_move: self.final_move,
+            policy: self.policy.clone(),
+            rng: Rng::with_seed(seed),
+        }
+    }
+
+    /// States that hash to the same value share a single node, whose
+    /// statistics are then updated through every parent leading to it.
+    /// Only states implementing [`State::hash_key`] are merged
+    pub fn set_transpositions(&mut self, enabled: bool) {
+        self.transpositions = enabled.then(|| {
+            self.nodes
+                .iter()
+                .filter_map(|node| Some((node.hash?, node.id)))
+                .collect()
+        });
+    }
+
+    /// Enabled by default. Note that with transpositions enabled, proofs
+    /// can be wrong for games where repeating positions end the game
+    pub fn set_solver(&mut self, enabled: bool) {
+        self.solver = enabled;
+    }
+
+    pub fn set_rave(&mut self, schedule: Option<RaveSchedule>) {
+        self.rave = schedule;
+    }
+
+    pub fn set_progressive_widening(&mut self, widening: Option<ProgressiveWidening>) {
+        self.widening = widening;
+    }
+
+    pub fn set_progressive_bias(&mut self, bias: Option<ProgressiveBias>) {
+        self.bias = bias;
+    }
+
+    /// See [`ImplicitMinimax`], only nodes added afterwards get evaluated
+    pub fn set_implicit_minimax(&mut self, minimax: Option<ImplicitMinimax<T>>) {
+        self.minimax = minimax;
+    }
+
+    pub fn set_hybrid_minimax(&mut self, hybrid: HybridMinimax) {
+        self.hybrid = hybrid;
+    }
+
+    pub fn hybrid_minimax(&self) -> HybridMinimax {
+        self.hybrid
+    }
+
+    pub fn set_final_move_selection(&mut self, selection: FinalMoveSelection) {
+        self.final_move = selection;
+    }
+
+    pub fn final_move_selection(&self) -> FinalMoveSelection {
+        self.final_move
+    }
+
+    pub fn set_bound(&mut self, bound: Option<TreeBound>) {
+        self.bound = bound;
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.bound.is_some_and(|bound| {
+            bound.max_nodes.is_some_and(|max| self.size() >= max)
+                || bound.max_memory.is_some_and(|max| self.memory >= max)
+        })
+    }
+
+    /// Prune the tree if it's full and bound to do so, should only be
+    /// called in between iterations as it invalidates the pruned ids
+    pub fn make_room(&mut self, root_id: usize) {
+        let Some(TreeBound {
+            max_nodes,
+            max_memory,
+            on_full: OnFull::Prune { target },
+        }) = self.bound
+        else {
+            return;
+        };
+
+        if !self.is_full() {
+            return;
+        }
+
+        let size = self.size();
+        let node_memory = self.memory / size.max(1);
+        let excess_nodes =
+            max_nodes.map_or(0, |max| size.saturating_sub((max as f32 * target) as usize));
+        let excess_memory = max_memory.map_or(0, |max| {
+            self.memory.saturating_sub((max as f32 * target) as usize) / node_memory.max(1)
+        });
+
+        self.prune(root_id, excess_nodes.max(excess_memory).max(1));
+    }
+
+    /// Cut at least `num_nodes` of the least visited nodes, along with their
+    /// subtrees. Their actions are returned to their parents' untried actions
+    /// and all nodes that became unreachable from the root are freed
+    pub fn prune(&mut self, root_id: usize, num_nodes: usize) {
+        // (simulations, parent, child), proven nodes are never cut
+        let mut edges = vec![];
+        for (id, node) in self.nodes.iter().enumerate() {
+            for &child_id in &node.child_ids {
+                let child = &self.nodes[child_id];
+                if child.proof().is_none() {
+                    edges.push((child.num_sims(), id, child_id));
+                }
+            }
+        }
+
+        edges.sort_unstable();
+        for &(_, parent_id, child_id) in edges.iter().take(num_nodes) {
+            self.cut_edge(parent_id, child_id);
+        }
+
+        let mut reachable = vec![false; self.nodes.len()];
+        let mut stack = vec![root_id];
+        reachable[root_id] = true;
+
+        while let Some(id) = stack.pop() {
+            for &child_id in &self.nodes[id].child_ids {
+                if !reachable[child_id] {
+                    reachable[child_id] = true;
+                    stack.push(child_id);
+                }
+            }
+        }
+
+        for &id in &self.free_ids {
+            reachable[id] = true;
+        }
+
+        let unreachable = reachable
+            .iter()
+            .enumerate()
+            .filter_map(|(id, &reachable)| (!reachable).then_some(id))
+            .collect::<Vec<_>>();
+
+        for id in unreachable {
+            self.free(id);
+        }
+
+        self.memory = self.live_memory();
+    }
+
+    /// Remove the edge between a parent and its child,
+    /// the action becomes untried again
+    fn cut_edge(&mut self, parent_id: usize, child_id: usize) {
+        let prior = self.nodes[child_id].prior;
+        let parent = &mut self.nodes[parent_id];
+        let Some(i) = parent.child_ids.iter().position(|&id| id == child_id) else {
+            return;
+        };
+
+        parent.child_ids.remove(i);
+        let action = parent.child_actions.remove(i);
+
+        // keep the untried actions sorted by prior
+        let j = parent.action_priors.partition_point(|&p| p < prior);
+        parent.actions.insert(j, action);
+        parent.action_priors.insert(j, prior);
+    }
+
+    fn free(&mut self, node_id: usize) {
+        let node = &mut self.nodes[node_id];
+
+        if let (Some(hash), Some(table)) = (node.hash, &mut self.transpositions) {
+            if table.get(&hash) == Some(&node_id) {
+                table.remove(&hash);
+            }
+        }
+
+        node.hash = None;
+        node.parent_id = None;
+        node.child_ids = vec![];
+        node.child_actions = vec![];
+        node.actions = vec![];
+        node.action_priors = vec![];
+
+        self.free_ids.push(node_id);
+    }
+
+    fn live_memory(&self) -> usize {
+        let free_memory = self.free_ids.len() * std::mem::size_of::<Node<T>>();
+        self.nodes.iter().map(Self::node_memory).sum::<usize>() - free_memory
+    }
+
+    pub fn size(&self) -> usize {
+        self.nodes.len() - self.free_ids.len()
+    }
+
+    /// Estimate of the memory used by the nodes in bytes, counting the node
+    /// structs, their untried actions and edges. Memory allocated by the
+    /// states themselves isn't included
+    pub fn memory_usage(&self) -> usize {
+        self.memory
+    }
+
+    fn node_memory(node: &Node<T>) -> usize {
+        std::mem::size_of::<Node<T>>()
+            + node.actions.capacity() * std::mem::size_of::<T::Action>()
+            + node.action_priors.capacity() * std::mem::size_of::<f32>()
+            + node.child_ids.len() * Self::edge_memory()
+    }
+
+    fn edge_memory() -> usize {
+        std::mem::size_of::<usize>() + std::mem::size_of::<T::Action>()
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.free_ids.clear();
+        self.memory = 0;
+
+        if let Some(table) = &mut self.transpositions {
+            table.clear();
+        }
+    }
+
+    /// Only keep the subtree starting at `node_id`, which becomes the new root.
+    /// Node ids are compacted, so the new root always has id 0
+    pub fn retain_subtree(&mut self, node_id: usize) -> usize {
+        let mut order = vec![node_id];
+        let mut new_ids = vec![None; self.nodes.len()];
+        new_ids[node_id] = Some(0);
+
+        let mut i = 0;
+        while i < order.len() {
+            for &child_id in &self.nodes[order[i]].child_ids {
+                // transpositions can be reached through multiple parents
+                if new_ids[child_id].is_none() {
+                    new_ids[child_id] = Some(order.len());
+                    order.push(child_id);
+                }
+            }
+
+            i += 1;
+        }
+
+        let mut old_nodes = self.nodes.drain(..).map(Some).collect::<Vec<_>>();
+        for old_id in order {
+            let mut node = old_nodes[old_id].take().unwrap();
+            node.id = new_ids[old_id].unwrap();
+            node.parent_id = node.parent_id.and_then(|parent_id| new_ids[parent_id]);
+            for child_id in node.child_ids.iter_mut() {
+                *child_id = new_ids[*child_id].unwrap();
+            }
+
+            self.nodes.push(node);
+        }
+
+        // a transposition whose first parent got discarded
+        // adopts one of its remaining parents
+        for id in 0..self.nodes.len() {
+            for i in 0..self.nodes[id].child_ids.len() {
+                let child_id = self.nodes[id].child_ids[i];
+                if child_id != 0 && self.nodes[child_id].parent_id.is_none() {
+                    self.nodes[child_id].parent_id = Some(id);
+                }
+            }
+        }
+
+        self.free_ids.clear();
+        self.memory = self.live_memory();
+        if let Some(table) = &mut self.transpositions {
+            *table = self
+                .nodes
+                .iter()
+                .filter_map(|node| Some((node.hash?, node.id)))
+                .collect();
+        }
+
+        0
+    }
+
+    /// Add a state reached from `parent_id`, when transpositions are enabled
+    /// and an equal state already is in the tree, that node gets linked to
+    /// the parent instead
+    pub fn add_state(&mut self, state: T, parent_id: Option<usize>) -> usize {
+        let hash = self.transpositions.as_ref().and_then(|_| state.hash_key());
+
+        if let Some(&id) = hash.and_then(|hash| self.transpositions.as_ref()?.get(&hash)) {
+            if let Some(parent) = parent_id {
+                self.nodes[parent].child_ids.push(id);
+                self.nodes[parent]
+                    .child_actions
+                    .push(state.last_action().unwrap());
+                self.memory += Self::edge_memory();
+            }
+
+            return id;
+        }
+
+        let id = self.free_ids.last().copied().unwrap_or(self.nodes.len());
+        if let Some(parent) = parent_id {
+            self.nodes[parent].child_ids.push(id);
+            self.nodes[parent]
+                .child_actions
+                .push(state.last_action().unwrap());
+            self.memory += Self::edge_memory();
+        }
+
+        // chance outcomes are moved into by whoever moved into the chance event
+        let player = parent_id.and_then(|parent_id| {
+            let parent = &self.nodes[parent_id];
+            match parent.is_chance() {
+                true => parent.player,
+                false => parent.state.current_player(),
+            }
+        });
+
+        let mut node = Node::new(state, id, parent_id, &mut self.rng);
+        node.hash = hash;
+        node.player = player;
+        // proofs only exist for two players taking turns
+        if self.solver && node.is_terminal() && node.state.current_player().is_none() {
+            node.set_proof(Proof::from_reward(node.state.reward(&node.state)));
+        }
+        if let (true, Some(depth)) = (self.solver, self.hybrid.expansion_depth) {
+            if !node.is_terminal() {
+                if let Some(proof) = solve_moves(&node.state, depth, &mut self.rng) {
+                    node.set_proof(proof);
+                }
+            }
+        }
+        if let Some(minimax) = &self.minimax {
+            node.set_minimax_value(match node.is_terminal() {
+                true => node.state.reward(&node.state),
+                false => minimax.evaluate(&node.state),
+            });
+        }
+
+        if let (Some(hash), Some(table)) = (hash, &mut self.transpositions) {
+            table.insert(hash, id);
+        }
+
+        self.memory += Self::node_memory(&node);
+        if self.free_ids.pop().is_some() {
+            self.nodes[id] = node;
+        } else {
+            self.nodes.push(node);
+        }
+
+        id
+    }
+
+    /// Descend from `node_id` until reaching a node that still has unexpanded
+    /// actions or is terminal. Returns the ids of all nodes along the way,
+    /// which is the path rewards should be backpropagated along
+    pub fn select_path(&mut self, node_id: usize) -> Vec<usize> {
+        let mut rng = std::mem::replace(&mut self.rng, Rng::with_seed(0));
+        let path = self.select_path_with(node_id, &mut rng, false);
+        self.rng = rng;
+
+        path
+    }
+
+    /// Like [`Tree::select_path`], but usable while the tree is shared between
+    /// threads. With `virtual_loss`, every node on the path gets a virtual loss
+    /// that has to be reverted after backpropagating
+    pub fn select_path_with(
+        &self,
+        mut node_id: usize,
+        rng: &mut Rng,
+        virtual_loss: bool,
+    ) -> Vec<usize> {
+        let mut path = vec![node_id];
+        if virtual_loss {
+            self.nodes[node_id].add_virtual_loss();
+        }
+
+        while self.is_fully_expanded(node_id)
+            && !self.is_terminal(node_id)
+            && self.nodes[node_id].proof().is_none()
+        {
+            // all children can be proven losses when they were proven through
+            // another parent, without this node being updated yet
+            let Some(child_id) = self.policy_select_child(node_id, rng) else {
+                break;
+            };
+            node_id = child_id;
+
+            // with transpositions, repeating positions form cycles
+            if path.contains(&node_id) {
+                break;
+            }
+
+            path.push(node_id);
+            if virtual_loss {
+                self.nodes[node_id].add_virtual_loss();
+            }
+
+            if self.solve_on_selection(node_id, rng) {
+                break;
+            }
+        }
+
+        path
+    }
+
+    /// MCTS-MS, see [`HybridMinimax::selection`]. Returns whether
+    /// the node got proven
+    fn solve_on_selection(&self, node_id: usize, rng: &mut Rng) -> bool {
+        let node = &self.nodes[node_id];
+        let Some((depth, visits)) = self.hybrid.selection else {
+            return false;
+        };
+        if !self.solver || node.num_sims() != visits || node.is_terminal() || node.proof().is_some()
+        {
+            return false;
+        }
+
+        match solve_moves(&node.state, depth, rng) {
+            Some(proof) => {
+                node.set_proof(proof);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn policy_select_child(&self, node_id: usize, rng: &mut Rng) -> Option<usize> {
+        let parent = &self.nodes[node_id];
+        if parent.is_chance() {
+            let priors = parent.child_ids.iter().map(|&id| self.nodes[id].prior);
+            return Some(parent.child_ids[sample_weighted(priors, rng)]);
+        }
+
+        let mut best = None;
+        let mut best_score = f32::NEG_INFINITY;
+
+        for &child_id in &parent.child_ids {
+            // never walk into a proven loss for the player to move
+            if matches!(self.nodes[child_id].proof(), Some(Proof::Loss(_))) {
+                continue;
+            }
+
+            let child = &self.nodes[child_id];
+            let mut score = self.policy.score(parent, child, self.value(child_id), rng);
+            if let Some(bias) = &self.bias {
+                score += bias.bias(child.prior, child.num_sims());
+            }
+
+            if best.is_none() || score > best_score {
+                best = Some(child_id);
+                best_score = score;
+            }
+        }
+
+        best
+    }
+
+    /// Estimated mean score of a node, used during selection
+    pub fn value(&self, node_id: usize) -> f32 {
+        let node = &self.nodes[node_id];
+
+        let value = match &self.rave {
+            Some(schedule) => schedule.blend(
+                node.mean_score(),
+                node.num_sims(),
+                node.amaf_mean_score(),
+                node.amaf_sims(),
+            ),
+            None => node.mean_score(),
+        };
+
+        match &self.minimax {
+            Some(minimax) => minimax.blend(value, node.num_sims(), node.minimax_value()),
+            None => value,
+        }
+    }
+
+    /// Will panic if this node does not have any possible actions left,
+    /// i.e. all it's children (possibly none) have already been added
+    /// to the tree
+    pub fn expand(&mut self, node_id: usize) -> usize {
+        if self.nodes[node_id].actions.is_empty()
+            || self.nodes[node_id].is_terminal()
+            || self.nodes[node_id].proof().is_some()
+            || self.is_full()
+        {
+            return node_id;
+        }
+
+        let (action, prior) = if self.nodes[node_id].is_chance() {
+            // sample from all outcomes, including those already expanded
+            let node = &self.nodes[node_id];
+            let priors = node.action_priors.iter().copied().chain(
+                node.child_ids
+                    .iter()
+                    .map(|&child_id| self.nodes[child_id].prior),
+            );
+            let i = sample_weighted(priors, &mut self.rng);
+
+            let node = &mut self.nodes[node_id];
+            if i >= node.actions.len() {
+                return node.child_ids[i - node.actions.len()];
+            }
+
+            (node.actions.remove(i), node.action_priors.remove(i))
+        } else {
+            let node = &mut self.nodes[node_id];
+            (
+                node.actions.pop().unwrap(),
+                node.action_priors.pop().unwrap(),
+            )
+        };
+        let new_state = self.nodes[node_id].state.apply_action(action);
+
+        let child_id = self.add_state(new_state, Some(node_id));
+        // transpositions keep the prior of the parent that created them
+        if self.nodes[child_id].parent_id == Some(node_id) {
+            self.nodes[child_id].prior = prior;
+        }
+
+        child_id
+    }
+
+    pub fn get_parent_id(&self, node_id: usize) -> Option<usize> {
+        self.nodes[node_id].parent_id
+    }
+
+    pub fn get_state(&self, node_id: usize) -> T
+    where
+        T: Clone,
+    {
+        self.nodes[node_id].state.clone()
+    }
+
+    pub fn get_state_ref(&self, node_id: usize) -> &T {
+        &self.nodes[node_id].state
+    }
+
+    pub fn get_child_states(&self, node_id: usize) -> impl Iterator<Item = T> + '_
+    where
+        T: Copy,
+    {
+        self.nodes[node_id]
+            .child_ids
+            .iter()
+            .map(|&child_id| self.nodes[child_id].state)
+    }
+
+    pub fn find_child(&self, node_id: usize, action: &T::Action) -> Option<usize> {
+        let node = &self.nodes[node_id];
+
+        node.child_actions
+            .iter()
+            .position(|child_action| child_action == action)
+            .map(|i| node.child_ids[i])
+    }
+
+    /// The action leading from `node_id` to its `i`'th child
+    pub fn child_action(&self, node_id: usize, i: usize) -> T::Action {
+        self.nodes[node_id].child_actions[i].clone()
+    }
+
+    /// Index of the most visited child in `child_ids`
+    fn most_visited_child(&self, node_id: usize) -> Option<usize> {
+        self.nodes[node_id]
+            .child_ids
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, &x)| self.nodes[x].num_sims())
+            .map(|(i, _)| i)
+    }
+
+    pub fn random_child(&mut self, node_id: usize) -> usize {
+        *self
+            .rng
+            .choice(self.nodes[node_id].child_ids.iter())
+            .unwrap_or(&node_id)
+    }
+
+    /// Whether selection should descend into the children of this node,
+    /// with progressive widening this can happen before all actions
+    /// have been expanded
+    pub fn is_fully_expanded(&self, node_id: usize) -> bool {
+        let node = &self.nodes[node_id];
+
+        match &self.widening {
+            Some(widening) if !node.child_ids.is_empty() => {
+                node.actions.is_empty()
+                    || node.child_ids.len() >= widening.max_children(node.num_sims())
+            }
+            _ => node.actions.is_empty(),
+        }
+    }
+
+    pub fn is_terminal(&self, node_id: usize) -> bool {
+        self.nodes[node_id].is_terminal()
+    }
+
+    pub fn update_node(&self, node_id: usize, reward: f32) {
+        self.nodes[node_id].update(reward);
+    }
+
+    /// The reward of a leaf, using its proof when it has one
+    /// and a rollout otherwise
+    pub fn leaf_reward(&self, node_id: usize, rollout: impl FnOnce(&T) -> Reward) -> Reward {
+        match self.nodes[node_id].proof() {
+            Some(proof) => Reward::TwoPlayer(proof.reward()),
+            None => rollout(&self.nodes[node_id].state),
+        }
+    }
+
+    /// Update all nodes on the path, in reverse order. Two player rewards are
+    /// from the perspective of the player who moved into the last node of the
+    /// path, with per player rewards every node gets the reward of the player
+    /// who moved into it (max^n). `playout` are the actions played by the
+    /// simulation after the path, only needed with RAVE
+    pub fn backpropagate(&self, path: &[usize], playout: &[T::Action], reward: &Reward) {
+        let reward = match reward {
+            Reward::TwoPlayer(reward) => *reward,
+            Reward::Players(rewards) => {
+                for &id in path {
+                    // nobody moved into the root, it only counts simulations
+                    let player = self.nodes[id].player;
+                    self.update_node(id, player.map_or(0., |player| rewards[player]));
+                }
+
+                return;
+            }
+        };
+
+        let mut node_reward = reward;
+        for (i, &id) in path.iter().enumerate().rev() {
+            self.update_node(id, node_reward);
+
+            // chance outcomes are scored like their parent
+            if i > 0 && !self.nodes[path[i - 1]].is_chance() {
+                node_reward = 1. - node_reward;
+            }
+        }
+
+        if self.rave.is_some() {
+            self.update_amaf(path, playout, reward);
+        }
+
+        if self.minimax.is_some() {
+            for &id in path.iter().rev().skip(1) {
+                self.update_minimax(id);
+            }
+        }
+
+        if self.solver {
+            for i in (0..path.len() - 1).rev() {
+                if self.nodes[path[i + 1]].proof().is_none() || !self.update_proof(path[i]) {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// The actions leading along a path of nodes
+    pub fn path_actions(&self, path: &[usize]) -> Vec<T::Action> {
+        path.windows(2)
+            .map(|window| {
+                let parent = &self.nodes[window[0]];
+                let i = parent
+                    .child_ids
+                    .iter()
+                    .position(|&child_id| child_id == window[1])
+                    .unwrap();
+
+                parent.child_actions[i].clone()
+            })
+            .collect()
+    }
+
+    /// For every node on the path, update the AMAF statistics of the children
+    /// whose action was played later on by the same player
+    fn update_amaf(&self, path: &[usize], playout: &[T::Action], reward: f32) {
+        let mut actions = self.path_actions(path);
+        actions.extend_from_slice(playout);
+
+        // actions played by the player to move at even and odd depths
+        let mut played = [HashSet::new(), HashSet::new()];
+        let leaf_depth = path.len() - 1;
+
+        for depth in (0..actions.len().max(path.len())).rev() {
+            if let Some(action) = actions.get(depth) {
+                played[depth % 2].insert(action);
+            }
+
+            let Some(&node_id) = path.get(depth) else {
+                continue;
+            };
+
+            // children are one ply deeper than their parent
+            let child_reward = if (leaf_depth - depth) % 2 == 1 {
+                reward
+            } else {
+                1. - reward
+            };
+
+            let node = &self.nodes[node_id];
+            for (child_id, action) in node.child_ids.iter().zip(&node.child_actions) {
+                if played[depth % 2].contains(action) {
+                    self.nodes[*child_id].update_amaf(child_reward);
+                }
+            }
+        }
+    }
+
+    /// Back up the minimax values of the children of a node, the player to
+    /// move picks the best child and chance events average their outcomes
+    fn update_minimax(&self, node_id: usize) {
+        let node = &self.nodes[node_id];
+        let children = node.child_ids.iter().map(|&id| &self.nodes[id]);
+
+        let value = if node.is_chance() {
+            let weight = children.clone().map(|child| child.prior).sum::<f32>();
+            children
+                .map(|child| child.prior * child.minimax_value())
+                .sum::<f32>()
+                / weight
+        } else {
+            match children.map(|child| child.minimax_value()).reduce(f32::max) {
+                Some(best) => 1. - best,
+                None => return,
+            }
+        };
+
+        node.set_minimax_value(value);
+    }
+
+    /// Try to prove a node from its children's proofs, returns
+    /// whether the node is proven
+    fn update_proof(&self, node_id: usize) -> bool {
+        let node = &self.nodes[node_id];
+        if node.proof().is_some() {
+            return true;
+        }
+
+        if node.is_chance() {
+            return self.update_chance_proof(node_id);
+        }
+
+        let mut fastest_win = None;
+        let mut slowest_loss = None;
+        let mut has_draw = false;
+        let mut all_proven = node.actions.is_empty();
+
+        for &child_id in &node.child_ids {
+            match self.nodes[child_id].proof() {
+                Some(Proof::Win(depth)) => {
+                    fastest_win = Some(fastest_win.map_or(depth, |d: u32| d.min(depth)))
+                }
+                Some(Proof::Loss(depth)) => {
+                    slowest_loss = Some(slowest_loss.map_or(depth, |d: u32| d.max(depth)))
+                }
+                Some(Proof::Draw) => has_draw = true,
+                None => all_proven = false,
+            }
+        }
+
+        // a winning move for the player to move here
+        // is a loss for the player who moved into this node
+        let proof = match (fastest_win, all_proven) {
+            (Some(depth), _) => Proof::Loss(depth + 1),
+            (None, true) if has_draw => Proof::Draw,
+            (None, true) => Proof::Win(slowest_loss.unwrap_or(0) + 1),
+            (None, false) => return false,
+        };
+
+        node.set_proof(proof);
+        true
+    }
+
+    /// A chance node is only proven when all of its
+    /// outcomes are expanded and have the same proof
+    fn update_chance_proof(&self, node_id: usize) -> bool {
+        let node = &self.nodes[node_id];
+        if !node.actions.is_empty() {
+            return false;
+        }
+
+        let mut proofs = node.child_ids.iter().map(|&id| self.nodes[id].proof());
+        let Some(Some(mut proof)) = proofs.next() else {
+            return false;
+        };
+
+        for other in proofs {
+            proof = match (proof, other) {
+                (Proof::Win(x), Some(Proof::Win(y))) => Proof::Win(x.max(y)),
+                (Proof::Loss(x), Some(Proof::Loss(y))) => Proof::Loss(x.max(y)),
+                (Proof::Draw, Some(Proof::Draw)) => Proof::Draw,
+                _ => return false,
+            };
+        }
+
+        node.set_proof(match proof {
+            Proof::Win(depth) => Proof::Win(depth + 1),
+            Proof::Loss(depth) => Proof::Loss(depth + 1),
+            Proof::Draw => Proof::Draw,
+        });
+        true
+    }
+
+    pub fn revert_virtual_loss(&self, path: &[usize]) {
+        for &id in path {
+            self.nodes[id].revert_virtual_loss();
+        }
+    }
+
+    /// The action chosen by the final move selection, except that proven
+    /// wins are always preferred and proven losses avoided whenever possible.
+    /// Panics when the node doesn't have any actions
+    pub fn best_action(&self, node_id: usize) -> T::Action {
+        self.final_action(node_id).0
+    }
+
+    /// [`Tree::best_action`], along with the criterion that chose it
+    pub fn final_action(&self, node_id: usize) -> (T::Action, SelectedBy) {
+        let child_ids = &self.nodes[node_id].child_ids;
+        let proofs = child_ids
+            .iter()
+            .map(|&child_id| self.nodes[child_id].proof())
+            .collect::<Vec<_>>();
+
+        let fastest_win = (0..child_ids.len())
+            .filter_map(|i| match proofs[i] {
+                Some(Proof::Win(depth)) => Some((i, depth)),
+                _ => None,
+            })
+            .min_by_key(|&(_, depth)| depth);
+        let not_lost = (0..child_ids.len())
+            .filter(|&i| !matches!(proofs[i], Some(Proof::Loss(_))))
+            .collect::<Vec<_>>();
+        let slowest_loss = (0..child_ids.len())
+            .filter_map(|i| match proofs[i] {
+                Some(Proof::Loss(depth)) => Some((i, depth)),
+                _ => None,
+            })
+            .max_by_key(|&(_, depth)| depth);
+
+        if let Some((i, _)) = fastest_win {
+            return (self.child_action(node_id, i), SelectedBy::Proof);
+        }
+
+        let stats = not_lost
+            .iter()
+            .map(|&i| {
+                let child = &self.nodes[child_ids[i]];
+                (child.num_sims(), child.mean_score())
+            })
+            .collect::<Vec<_>>();
+        if let Some((j, selected_by)) = self.final_move.select(&stats) {
+            return (self.child_action(node_id, not_lost[j]), selected_by);
+        }
+
+        match slowest_loss {
+            Some((i, _)) => (self.child_action(node_id, i), SelectedBy::Proof),
+            // stopped before the first iteration, fall back to the most promising action
+            None => (
+                self.nodes[node_id].actions.last().unwrap().clone(),
+                SelectedBy::Prior,
+            ),
+        }
+    }
+
+    /// Snapshot of the search from `root_id`, not marked as finished
+    pub fn search_info(
+        &self,
+        root_id: usize,
+        iterations: usize,
+        elapsed: Duration,
+    ) -> SearchInfo<T::Action> {
+        let root = &self.nodes[root_id];
+        let children = root
+            .child_ids
+            .iter()
+            .enumerate()
+            .map(|(i, &child_id)| {
+                let child = &self.nodes[child_id];
+                ChildInfo {
+                    action: self.child_action(root_id, i),
+                    num_sims: child.num_sims(),
+                    mean_score: child.mean_score(),
+                    std_dev: Some(child.variance().sqrt()),
+                    proof: child.proof(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let has_actions = !root.child_ids.is_empty() || !root.actions.is_empty();
+        let (best_action, selected_by) = has_actions.then(|| self.final_action(root_id)).unzip();
+        let value = best_action.as_ref().and_then(|action| {
+            let child_id = self.find_child(root_id, action)?;
+            let child = &self.nodes[child_id];
+            child.is_explored().then(|| child.mean_score())
+        });
+        let pv = self.continuation(root_id);
+
+        SearchInfo {
+            iterations,
+            elapsed,
+            nodes: self.size(),
+            memory: self.memory_usage(),
+            depth: pv.len(),
+            best_action,
+            value,
+            proof: root.proof().map(Proof::invert),
+            pv,
+            children,
+            selected_by,
+            thread_iterations: vec![],
+            finished: false,
+        }
+    }
+
+    pub fn continuation(&self, mut node_id: usize) -> Vec<T::Action> {
+        let mut actions = vec![];
+        let mut visited = vec![node_id];
+
+        while let Some(i) = self.most_visited_child(node_id) {
+            actions.push(self.child_action(node_id, i));
+            node_id = self.nodes[node_id].child_ids[i];
+
+            if visited.contains(&node_id) {
+                break;
+            }
+            visited.push(node_id);
+        }
+
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fastrand::Rng;
+
+    use crate::{
+        limits::SearchLimits,
+        node::Proof,
+        state::{
+            tests::{quiet_mcts, TicTacToe},
+            State,
+        },
+    };
+
+    #[test]
+    fn test_transpositions_share_nodes() {
+        let mut searcher = quiet_mcts();
+        searcher.set_transpositions(true);
+        searcher.search(&SearchLimits::iterations(2000), TicTacToe::default());
+
+        let tree = searcher.tree();
+        let num_edges = tree
+            .nodes
+            .iter()
+            .map(|node| node.child_ids.len())
+            .sum::<usize>();
+
+        assert!(num_edges > tree.size() - 1);
+    }
+
+    #[test]
+    fn test_solver_proves_win() {
+        let mut searcher = quiet_mcts();
+
+        assert_eq!(
+            searcher.search(&SearchLimits::millis(1000), TicTacToe::win_in_one()),
+            2
+        );
+        assert_eq!(searcher.proven_result(), Some(Proof::Win(1)));
+    }
+
+    /// The player picks a coin, which lands heads with probability 0.3
+    /// for coin 0 and 0.6 for coin 1. Heads wins
+    #[derive(Clone)]
+    enum Coin {
+        Pick,
+        Flip(usize),
+        Landed(bool),
+    }
+
+    impl State for Coin {
+        type Action = usize;
+
+        fn possible_actions(&self, rng: &mut Rng) -> Vec<Self::Action> {
+            let mut actions = match self {
+                Coin::Pick => vec![0, 1],
+                _ => vec![],
+            };
+            rng.shuffle(&mut actions);
+
+            actions
+        }
+
+        fn chance_outcomes(&self) -> Option<Vec<(Self::Action, f32)>> {
+            let Coin::Flip(coin) = self else {
+                return None;
+            };
+
+            let p = [0.3, 0.6][*coin];
+            Some(vec![(1, p), (0, 1. - p)])
+        }
+
+        fn apply_action(&self, action: Self::Action) -> Self {
+            match self {
+                Coin::Pick => Coin::Flip(action),
+                _ => Coin::Landed(action == 1),
+            }
+        }
+
+        fn last_action(&self) -> Option<Self::Action> {
+            match self {
+                Coin::Pick => None,
+                Coin::Flip(coin) => Some(*coin),
+                Coin::Landed(heads) => Some(*heads as usize),
+            }
+        }
+
+        fn reward(&self, _perspective: &Self) -> f32 {
+            match self {
+                Coin::Landed(true) => 1.,
+                _ => 0.,
+            }
+        }
+
+        fn is_terminal(&mut self, _depth: usize) -> bool {
+            matches!(self, Coin::Landed(_))
+        }
+    }
+
+    #[test]
+    fn test_chance_nodes_sample_outcomes() {
+        let mut searcher = quiet_mcts();
+        searcher.set_seed(5);
+
+        assert_eq!(
+            searcher.search(&SearchLimits::iterations(3000), Coin::Pick),
+            1
+        );
+        assert_eq!(searcher.proven_result(), None);
+
+        let tree = searcher.tree();
+        let flip = &tree.nodes[tree.find_child(0, &1).unwrap()];
+        let heads = &tree.nodes[tree.find_child(flip.id, &1).unwrap()];
+        let heads_ratio = heads.num_sims() as f32 / flip.num_sims() as f32;
+
+        assert!(flip.is_chance());
+        assert_eq!(flip.child_ids.len(), 2);
+        assert!((heads_ratio - 0.6).abs() < 0.05);
+        assert!((flip.mean_score() - 0.6).abs() < 0.05);
+        assert_eq!(heads.mean_score(), 1.);
+    }
+
+    /// Three players take one or two stones from a pile, whoever takes the
+    /// last stone wins. Taking a single stone gives another turn
+    #[derive(Clone)]
+    struct Pile {
+        stones: usize,
+        player: usize,
+        last: Option<usize>,
+        winner: Option<usize>,
+    }
+
+    impl State for Pile {
+        type Action = usize;
+
+        fn possible_actions(&self, rng: &mut Rng) -> Vec<Self::Action> {
+            let mut actions = (1..=self.stones.min(2)).collect::<Vec<_>>();
+            rng.shuffle(&mut actions);
+
+            actions
+        }
+
+        fn apply_action(&self, action: Self::Action) -> Self {
+            let stones = self.stones - action;
+            Pile {
+                stones,
+                player: if action == 1 {
+                    self.player
+                } else {
+                    (self.player + 1) % 3
+                },
+                last: Some(action),
+                winner: (stones == 0).then_some(self.player),
+            }
+        }
+
+        fn last_action(&self) -> Option<Self::Action> {
+            self.last
+        }
+
+        fn reward(&self, _perspective: &Self) -> f32 {
+            unreachable!()
+        }
+
+        fn current_player(&self) -> Option<usize> {
+            Some(self.player)
+        }
+
+        fn rewards(&self) -> Vec<f32> {
+            (0..3)
+                .map(|player| (self.winner == Some(player)) as usize as f32)
+                .collect()
+        }
+
+        fn is_terminal(&mut self, _depth: usize) -> bool {
+            self.winner.is_some()
+        }
+    }
+
+    #[test]
+    fn test_player_rewards() {
+        let pile = Pile {
+            stones: 3,
+            player: 0,
+            last: None,
+            winner: None,
+        };
+        let mut searcher = quiet_mcts();
+        searcher.set_seed(1);
+
+        assert_eq!(searcher.search(&SearchLimits::iterations(500), pile), 1);
+
+        let tree = searcher.tree();
+        let take_one = &tree.nodes[tree.find_child(0, &1).unwrap()];
+        let take_two = &tree.nodes[tree.find_child(0, &2).unwrap()];
+
+        // taking one stone always wins eventually, taking two never does
+        assert_eq!(take_one.player, Some(0));
+        assert_eq!(take_one.mean_score(), 1.);
+        assert_eq!(take_two.mean_score(), 0.);
+        for &child_id in &take_one.child_ids {
+            assert_eq!(tree.nodes[child_id].player, Some(0));
+        }
+        for &child_id in &take_two.child_ids {
+            assert_eq!(tree.nodes[child_id].player, Some(1));
+            assert_eq!(tree.nodes[child_id].mean_score(), 1.);
+        }
+    }
+}